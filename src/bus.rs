@@ -0,0 +1,34 @@
+// everything the cpu talks to goes through a bus, flat ram is the plain MEMORY
+use crate::cpu::MEMORY;
+use crate::{Byte, Word};
+
+pub trait Bus {
+    // a read the cpu makes, which may have side effects on memory mapped devices
+    fn read(&mut self, address: Word) -> Byte;
+    fn write(&mut self, address: Word, value: Byte);
+    // a read without side effects, for debuggers and the disassembler
+    fn peek(&self, address: Word) -> Byte;
+    // lets the devices on the bus run for the cycles an instruction took
+    fn tick(&mut self, _cycles: u32) {}
+    // whether anything on the bus is holding the irq or nmi line
+    fn irq(&self) -> bool {
+        false
+    }
+    fn nmi(&self) -> bool {
+        false
+    }
+}
+
+impl Bus for MEMORY {
+    fn read(&mut self, address: Word) -> Byte {
+        self.get_byte(address)
+    }
+
+    fn write(&mut self, address: Word, value: Byte) {
+        self.set_byte(address, value);
+    }
+
+    fn peek(&self, address: Word) -> Byte {
+        self.get_byte(address)
+    }
+}
@@ -1,11 +1,20 @@
 use std::{
+    error::Error,
+    fmt,
     fs::File,
     io::{Read, Write},
 };
 
-use crate::{Byte, Word, MEMORY_RANGE, STACK_HIGH};
+use crate::bus::Bus;
+use crate::opcodes::{opcode, Mnemonic, Opcode};
+use crate::{Byte, Word, IRQ_VECTOR, MEMORY_RANGE, NMI_VECTOR, RESET_VECTOR, STACK_LOW};
+
+mod addressing;
+
+use addressing::InstructionArgs;
 
 #[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub struct MEMORY {
     pub data: [Byte; MEMORY_RANGE],
 }
@@ -32,7 +41,7 @@ impl MEMORY {
 }
 
 #[allow(dead_code)]
-
+#[derive(Default)]
 pub struct Status {
     pub n: bool, //negative
     pub v: bool, //overflow
@@ -44,650 +53,1168 @@ pub struct Status {
     pub c: bool, //carry
 }
 
-impl Default for Status {
-    fn default() -> Self {
+impl Status {
+    pub const BREAK: Byte = 0b0001_0000;
+    pub const UNUSED: Byte = 0b0010_0000;
+
+    // bitshifter's paradise, laid out NV1BDIZC with bit 5 always set
+    pub fn to_byte(&self) -> Byte {
+        let mut byte = Status::UNUSED;
+        byte |= (self.n as u8) << 7;
+        byte |= (self.v as u8) << 6;
+        byte |= (self.b as u8) << 4;
+        byte |= (self.d as u8) << 3;
+        byte |= (self.i as u8) << 2;
+        byte |= (self.z as u8) << 1;
+        byte |= self.c as u8;
+        byte
+    }
+
+    // the break bit only exists on pushed copies so it is dropped on the way back in
+    pub fn from_byte(byte: Byte) -> Self {
         Self {
-            n: false,
-            v: false,
-            u: false,
+            n: (byte & 0b1000_0000) != 0,
+            v: (byte & 0b0100_0000) != 0,
+            u: true,
             b: false,
-            d: false,
-            i: false,
-            z: false,
-            c: false,
+            d: (byte & 0b0000_1000) != 0,
+            i: (byte & 0b0000_0100) != 0,
+            z: (byte & 0b0000_0010) != 0,
+            c: (byte & 0b0000_0001) != 0,
         }
     }
+
+    // n and z mirror the result of almost every instruction
+    pub fn set_zn(&mut self, result: Byte) {
+        self.z = result == 0;
+        self.n = (result & 0b1000_0000) != 0;
+    }
+
+    // compares subtract without storing the result, carry means no borrow was needed
+    pub fn set_compare(&mut self, register: Byte, data: Byte) {
+        self.c = register >= data;
+        self.set_zn(register.wrapping_sub(data));
+    }
+
+    // flags of a binary add, sum is lhs + rhs + carry before truncation
+    pub fn set_add(&mut self, lhs: Byte, rhs: Byte, sum: Word) {
+        let result = sum as Byte;
+        self.c = sum > 0xFF;
+        // overflow when both inputs share a sign that the result does not
+        self.v = ((lhs ^ result) & (rhs ^ result) & 0b1000_0000) != 0;
+        self.set_zn(result);
+    }
 }
 
-impl Status {
-    // bitshifter's paradise
-    pub fn to_byte(&self) -> Byte {
-        let mut byte = 0x00;
-        byte |= (self.n as u8) << 0;
-        byte |= (self.v as u8) << 1;
-        byte |= (self.u as u8) << 2;
-        byte |= (self.b as u8) << 3;
-        byte |= (self.d as u8) << 4;
-        byte |= (self.i as u8) << 5;
-        byte |= (self.z as u8) << 6;
-        byte |= (self.c as u8) << 7;
-        byte
+// which member of the 6502 family is being emulated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    #[default]
+    Nmos, // original MOS 6502
+    Cmos, // WDC/Rockwell 65C02
+}
+
+// how adc and sbc treat the decimal flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecimalMode {
+    #[default]
+    Nmos, // bcd, n v and z are left as the nmos adder produces them
+    Cmos,     // bcd with n and z taken from the corrected result
+    Disabled, // the decimal flag is ignored, as on the ricoh 2a03
+}
+
+// the undocumented opcodes whose results differ between chips
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unstable {
+    pub xaa_magic: Byte, // xaa: a = (a | magic) & x & immediate
+    pub lxa_magic: Byte, // lxa: a = x = (a | magic) & immediate
+    // sha, shx, shy and tas store the high byte of the address plus one anded in,
+    // and when indexing crosses a page the stored value replaces that high byte
+    pub sh_and_high: bool,
+    pub sh_page_cross: bool,
+}
+
+impl Default for Unstable {
+    fn default() -> Self {
+        Self {
+            xaa_magic: 0xEE,
+            lxa_magic: 0xEE,
+            sh_and_high: true,
+            sh_page_cross: true,
+        }
     }
 }
 
+// what to do with an opcode the cpu has no instruction for, the undocumented
+// nmos opcodes count unless they are turned on with CPU::undocumented
+#[derive(Default)]
+pub enum IllegalOpcodePolicy {
+    // skip it along with its operand bytes
+    #[default]
+    Nop,
+    // stop the cpu and keep the error in CPU::fault
+    Halt,
+    // hand the error back from try_execute and leave the pc on the opcode, execute,
+    // step and run_for_cycles stop and keep it in CPU::fault
+    Error,
+    // tell someone, then skip it like a nop
+    Callback(Box<dyn FnMut(&ExecError)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecError {
+    IllegalOpcode { pc: Word, opcode: Byte },
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExecError::IllegalOpcode { pc, opcode } => {
+                write!(f, "illegal opcode ${:02X} at ${:04X}", opcode, pc)
+            }
+        }
+    }
+}
+
+impl Error for ExecError {}
+
+// reported when the stack pointer wraps around page one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackEvent {
+    Overflow { pc: Word },  // pushed past $0100 into $01FF
+    Underflow { pc: Word }, // pulled past $01FF into $0100
+}
+
 #[allow(dead_code)]
+#[allow(clippy::upper_case_acronyms)]
 pub struct CPU {
     pub acc: Byte, //accumulator
     pub x: Byte,   //index
     pub y: Byte,   //index
 
-    pub stkptr: Word,
+    pub stkptr: Byte, // offset into page one
     pub prgmctr: Word,
 
     pub status: Status,
-}
 
-#[derive(Clone, Copy)]
-enum InstructionArgs {
-    None,
-    OneByte(Byte),
-    TwoByte(Byte, Byte),
-    Address(Word),
+    pub variant: Variant,
+    pub decimal_mode: DecimalMode,
+
+    pub cycles: u64, // clock cycles run since the cpu was created
+
+    pub undocumented: bool, // run the undocumented nmos opcodes instead of skipping them
+    pub unstable: Unstable,
+    pub halted: bool, // jam or stp, only a reset gets going again
+
+    pub illegal_opcodes: IllegalOpcodePolicy,
+    pub fault: Option<ExecError>, // the illegal opcode the cpu stopped on, if any
+
+    pub stack_diagnostics: bool, // report stack wraparound through take_stack_event
+    stack_event: Option<StackEvent>,
+
+    page_crossed: bool, // set by the indexed addressing modes
+    extra_cycles: u32,  // branch and decimal penalties of the current instruction
+
+    irq_line: bool, // level triggered, held by whoever is asking for service
+    nmi_line: bool, // edge triggered, only the assert edge matters
+    nmi_pending: bool,
+    bus_nmi_line: bool, // the nmi line as the devices on the bus last left it
 }
 
-type InstructionHandler = fn(&mut CPU, &mut MEMORY, Byte, Byte);
 trait InstructionExecutor<T> {
-    fn execute_with_args(&mut self, memory: &mut MEMORY, args: T);
+    fn execute_with_args<B: Bus>(
+        &mut self,
+        memory: &mut B,
+        mnemonic: Mnemonic,
+        args: T,
+    ) -> Option<Byte>;
 }
 
 impl InstructionExecutor<InstructionArgs> for CPU {
-    fn execute_with_args(&mut self, memory: &mut MEMORY, args: InstructionArgs) {
-        match args {
-            InstructionArgs::None => {}
-            InstructionArgs::OneByte(byte) => {}
-            InstructionArgs::TwoByte(byte1, byte2) => {}
-            InstructionArgs::Address(address) => {}
+    // runs a decoded instruction, returns the pulled byte for the pull instructions
+    fn execute_with_args<B: Bus>(
+        &mut self,
+        m: &mut B,
+        mnemonic: Mnemonic,
+        args: InstructionArgs,
+    ) -> Option<Byte> {
+        match mnemonic {
+            Mnemonic::Lda => self.lda(self.read(m, args)),
+            Mnemonic::Ldx => self.ldx(self.read(m, args)),
+            Mnemonic::Ldy => self.ldy(self.read(m, args)),
+            Mnemonic::Sta => self.sta(m, args.address()),
+            Mnemonic::Stx => self.stx(m, args.address()),
+            Mnemonic::Sty => self.sty(m, args.address()),
+            Mnemonic::Stz => self.stz(m, args.address()),
+
+            Mnemonic::Adc => self.adc(self.read(m, args)),
+            Mnemonic::Sbc => self.sbc(self.read(m, args)),
+            Mnemonic::And => self.and(self.read(m, args)),
+            Mnemonic::Ora => self.ora(self.read(m, args)),
+            Mnemonic::Eor => self.eor(self.read(m, args)),
+            Mnemonic::Cmp => self.cmp(self.read(m, args)),
+            Mnemonic::Cpx => self.cpx(self.read(m, args)),
+            Mnemonic::Cpy => self.cpy(self.read(m, args)),
+            Mnemonic::Bit => match args {
+                InstructionArgs::OneByte(data) => self.bit_immediate(data),
+                _ => self.bit(self.read(m, args)),
+            },
+
+            Mnemonic::Asl => self.modify(m, args, CPU::asl),
+            Mnemonic::Lsr => self.modify(m, args, CPU::lsr),
+            Mnemonic::Rol => self.modify(m, args, CPU::rol),
+            Mnemonic::Ror => self.modify(m, args, CPU::ror),
+            Mnemonic::Inc => self.modify(m, args, CPU::inc),
+            Mnemonic::Dec => self.modify(m, args, CPU::dec),
+            Mnemonic::Tsb => self.modify(m, args, CPU::tsb),
+            Mnemonic::Trb => self.modify(m, args, CPU::trb),
+            Mnemonic::Rmb(bit) => {
+                let address = args.address();
+                let data = m.read(address);
+                m.write(address, data & !(1 << bit));
+            }
+            Mnemonic::Smb(bit) => {
+                let address = args.address();
+                let data = m.read(address);
+                m.write(address, data | (1 << bit));
+            }
+
+            Mnemonic::Inx => self.inx(),
+            Mnemonic::Iny => self.iny(),
+            Mnemonic::Dex => self.dex(),
+            Mnemonic::Dey => self.dey(),
+            Mnemonic::Tax => self.tax(),
+            Mnemonic::Tay => self.tay(),
+            Mnemonic::Txa => self.txa(),
+            Mnemonic::Tya => self.tya(),
+            Mnemonic::Tsx => self.tsx(),
+            Mnemonic::Txs => self.txs(),
+
+            Mnemonic::Pha => self.pha(m),
+            Mnemonic::Php => self.php(m),
+            Mnemonic::Phx => self.phx(m),
+            Mnemonic::Phy => self.phy(m),
+            Mnemonic::Pla => return Some(self.pla(m)),
+            Mnemonic::Plp => return Some(self.plp(m)),
+            Mnemonic::Plx => return Some(self.plx(m)),
+            Mnemonic::Ply => return Some(self.ply(m)),
+
+            Mnemonic::Bcc => self.branch(!self.status.c, args.byte()),
+            Mnemonic::Bcs => self.branch(self.status.c, args.byte()),
+            Mnemonic::Bne => self.branch(!self.status.z, args.byte()),
+            Mnemonic::Beq => self.branch(self.status.z, args.byte()),
+            Mnemonic::Bpl => self.branch(!self.status.n, args.byte()),
+            Mnemonic::Bmi => self.branch(self.status.n, args.byte()),
+            Mnemonic::Bvc => self.branch(!self.status.v, args.byte()),
+            Mnemonic::Bvs => self.branch(self.status.v, args.byte()),
+            Mnemonic::Bra => self.branch(true, args.byte()),
+            Mnemonic::Bbr(bit) | Mnemonic::Bbs(bit) => {
+                if let InstructionArgs::TwoByte(address, offset) = args {
+                    let set = m.read(make_address(0x00, address)) & (1 << bit) != 0;
+                    self.branch(set == matches!(mnemonic, Mnemonic::Bbs(_)), offset);
+                }
+            }
+
+            Mnemonic::Jmp => self.jmp(args.address()),
+            Mnemonic::Jsr => self.jsr(m, args.address()),
+            Mnemonic::Rts => self.rts(m),
+            Mnemonic::Rti => self.rti(m),
+            Mnemonic::Brk => self.brk(m),
+
+            Mnemonic::Clc => self.clc(),
+            Mnemonic::Sec => self.sec(),
+            Mnemonic::Cli => self.cli(),
+            Mnemonic::Sei => self.sei(),
+            Mnemonic::Clv => self.clv(),
+            Mnemonic::Cld => self.cld(),
+            Mnemonic::Sed => self.sed(),
+
+            // wai is not modelled, it falls through like a nop
+            Mnemonic::Nop | Mnemonic::Wai => self.nop(),
+            Mnemonic::Stp | Mnemonic::Jam => self.halt(),
+
+            // undocumented nmos opcodes
+            Mnemonic::Lax => self.lax(self.read(m, args)),
+            Mnemonic::Sax => m.write(args.address(), self.acc & self.x),
+            Mnemonic::Slo => self.modify(m, args, CPU::slo),
+            Mnemonic::Rla => self.modify(m, args, CPU::rla),
+            Mnemonic::Sre => self.modify(m, args, CPU::sre),
+            Mnemonic::Rra => self.modify(m, args, CPU::rra),
+            Mnemonic::Dcp => self.modify(m, args, CPU::dcp),
+            Mnemonic::Isc => self.modify(m, args, CPU::isc),
+            Mnemonic::Anc => self.anc(args.byte()),
+            Mnemonic::Alr => self.alr(args.byte()),
+            Mnemonic::Arr => self.arr(args.byte()),
+            Mnemonic::Sbx => self.sbx(args.byte()),
+            Mnemonic::Xaa => self.xaa(args.byte()),
+            Mnemonic::Lxa => self.lxa(args.byte()),
+            Mnemonic::Las => self.las(self.read(m, args)),
+            Mnemonic::Sha => self.store_high(m, args.address(), self.acc & self.x),
+            Mnemonic::Shx => self.store_high(m, args.address(), self.x),
+            Mnemonic::Shy => self.store_high(m, args.address(), self.y),
+            Mnemonic::Tas => {
+                self.stkptr = self.acc & self.x;
+                self.store_high(m, args.address(), self.stkptr);
+            }
         }
+        None
     }
 }
 
-fn generic_handler<F, T>(
-    cpu: &mut CPU,
-    memory: &mut MEMORY,
-    operand1: Byte,
-    operand2: Byte,
-    executor: F,
-    arg_transformer: fn(Byte, Byte) -> T,
-) where
-    F: Fn(&mut CPU, &mut MEMORY, T),
-{
-    let args = arg_transformer(operand1, operand2);
-    executor(cpu, memory, args);
-}
-
 #[allow(dead_code)]
 impl CPU {
     pub fn new() -> Self {
         //sets registers to 0 aside from stack pointer
-        //sets stack pointer to 0x01FF
+        //sets stack pointer to 0xFF, the top of page one
         Self {
             acc: Byte::default(),
             x: Byte::default(),
             y: Byte::default(),
-            stkptr: STACK_HIGH,
+            stkptr: 0xFF,
             prgmctr: Word::default(),
             status: Status::default(),
+            variant: Variant::default(),
+            decimal_mode: DecimalMode::default(),
+            cycles: 0,
+            undocumented: false,
+            unstable: Unstable::default(),
+            halted: false,
+            illegal_opcodes: IllegalOpcodePolicy::default(),
+            fault: None,
+            stack_diagnostics: false,
+            stack_event: None,
+            page_crossed: false,
+            extra_cycles: 0,
+            irq_line: false,
+            nmi_line: false,
+            nmi_pending: false,
+            bus_nmi_line: false,
         }
     }
 
-    const INSTRUCTION_TABLE: [Option<InstructionHandler>; 256] = {
-        let mut table = [None; 256];
-
-        // LDA variants
-        table[0xA9] = Some(CPU::handle_lda_immediate as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xAD] = Some(CPU::handle_lda_absolute as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xBD] = Some(CPU::handle_lda_absolute_x as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xB9] = Some(CPU::handle_lda_absolute_y as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xA5] = Some(CPU::handle_lda_zero_page as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xB5] = Some(CPU::handle_lda_zero_page_x as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xA1] =
-            Some(CPU::handle_lda_indexed_indirect as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xB1] =
-            Some(CPU::handle_lda_indirect_indexed as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xA2] = Some(CPU::handle_ldx_immediate as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xA0] = Some(CPU::handle_ldy_immediate as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0x4C] = Some(CPU::handle_jmp_absolute as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0x48] = Some(CPU::handle_pha as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0x68] = Some(CPU::handle_pla as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xEA] = Some(CPU::handle_nop as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0x08] = Some(CPU::handle_php as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0x28] = Some(CPU::handle_plp as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xAA] = Some(CPU::handle_tax as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0x8A] = Some(CPU::handle_txa as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xCA] = Some(CPU::handle_dex as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xE8] = Some(CPU::handle_inx as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0x98] = Some(CPU::handle_tya as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0x88] = Some(CPU::handle_dey as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xC8] = Some(CPU::handle_iny as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0x86] = Some(CPU::handle_stx as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0x84] = Some(CPU::handle_sty as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0x85] = Some(CPU::handle_sta as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0x00] = Some(CPU::handle_brk as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0x9A] = Some(CPU::handle_txs as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-        table[0xBA] = Some(CPU::handle_tsx as fn(&mut CPU, &mut MEMORY, Byte, Byte));
-
-        table
-    };
+    pub fn with_variant(variant: Variant) -> Self {
+        let decimal_mode = match variant {
+            Variant::Nmos => DecimalMode::Nmos,
+            Variant::Cmos => DecimalMode::Cmos,
+        };
+        Self {
+            variant,
+            decimal_mode,
+            ..Self::new()
+        }
+    }
 
-    pub fn reset(&mut self) {
+    // clears the registers and starts from the address in the reset vector
+    pub fn reset<B: Bus>(&mut self, memory: &B) {
         self.acc = Byte::default();
         self.x = Byte::default();
         self.y = Byte::default();
 
-        self.stkptr = STACK_HIGH; // all for the stack !!!
-        self.prgmctr = Word::default();
+        self.stkptr = 0xFF; // all for the stack !!!
+
+        // peeked so a reset can be done from a shared borrow
+        let low = memory.peek(RESET_VECTOR);
+        let high = memory.peek(RESET_VECTOR.wrapping_add(1));
+        self.prgmctr = make_address(high, low);
 
         self.status.v = bool::default();
         self.status.n = bool::default();
         self.status.c = bool::default();
         self.status.z = bool::default();
-        self.status.i = bool::default();
+        self.status.i = true;
         self.status.d = bool::default();
         self.status.b = bool::default();
+
+        self.nmi_pending = false;
+        self.halted = false;
+        self.fault = None;
     }
 
-    fn handle_lda_immediate(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.lda(operand1);
+    // drives the irq line, it stays asserted until the device lets go
+    pub fn set_irq(&mut self, asserted: bool) {
+        self.irq_line = asserted;
     }
-    fn handle_lda_absolute(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        let concat_byte = make_address(operand1, operand2);
-        let data = memory.get_byte(concat_byte);
-        cpu.lda(data);
+
+    // drives the nmi line, an nmi is latched each time it becomes asserted
+    pub fn set_nmi(&mut self, asserted: bool) {
+        if asserted && !self.nmi_line {
+            self.nmi_pending = true;
+        }
+        self.nmi_line = asserted;
     }
 
-    fn handle_lda_absolute_x(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        let concat_byte = make_address(operand1, operand2) + cpu.x as u16;
-        let data = memory.get_byte(concat_byte);
-        cpu.lda(data);
+    // services a maskable interrupt unless the i flag is set
+    pub fn irq<B: Bus>(&mut self, memory: &mut B) {
+        if !self.status.i {
+            self.interrupt(memory, IRQ_VECTOR, false);
+        }
     }
 
-    fn handle_lda_absolute_y(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        let concat_byte = make_address(operand1, operand2) + cpu.y as u16;
-        let data = memory.get_byte(concat_byte);
-        cpu.lda(data);
+    // services a non maskable interrupt
+    pub fn nmi<B: Bus>(&mut self, memory: &mut B) {
+        self.interrupt(memory, NMI_VECTOR, false);
     }
 
-    fn handle_lda_zero_page(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        let data = memory.get_byte(make_address(0x00, operand1));
-        cpu.lda(data);
+    // pushes the return address and status then jumps through the vector
+    fn interrupt<B: Bus>(&mut self, memory: &mut B, vector: Word, brk: bool) {
+        let (high, low) = split_address(self.prgmctr);
+        self.push(memory, high);
+        self.push(memory, low);
+
+        let status = self.status.to_byte();
+        self.push(memory, if brk { status | Status::BREAK } else { status });
+
+        self.status.i = true;
+        if self.variant == Variant::Cmos {
+            self.status.d = false;
+        }
+        self.prgmctr = CPU::read_word(memory, vector);
     }
 
-    fn handle_lda_zero_page_x(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        let data = memory.get_byte(make_address(0x00, operand1) + cpu.x as u16);
-        cpu.lda(data);
+    // nmi wins over irq, both are only checked between instructions, the lines are
+    // shared between set_irq and set_nmi and whatever devices are on the bus
+    fn poll_interrupts<B: Bus>(&mut self, memory: &mut B) -> bool {
+        let bus_nmi = memory.nmi();
+        if bus_nmi && !self.bus_nmi_line {
+            self.nmi_pending = true;
+        }
+        self.bus_nmi_line = bus_nmi;
+
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            self.nmi(memory);
+            true
+        } else if (self.irq_line || memory.irq()) && !self.status.i {
+            self.irq(memory);
+            true
+        } else {
+            false
+        }
     }
-    fn handle_lda_indexed_indirect(
-        cpu: &mut CPU,
-        memory: &mut MEMORY,
-        operand1: Byte,
-        operand2: Byte,
-    ) {
-        cpu.x = cpu.x + operand1;
-        let new_operand1 = memory.get_byte(make_address(0x00, cpu.x));
-        let new_operand2 = memory.get_byte(make_address(0x00, operand2));
-        let data = memory.get_byte(make_address(new_operand1, new_operand2));
-        cpu.lda(data);
-    }
-
-    fn handle_lda_indirect_indexed(
-        cpu: &mut CPU,
-        memory: &mut MEMORY,
-        operand1: Byte,
-        operand2: Byte,
+
+    fn read_modify_write<B: Bus>(
+        &mut self,
+        memory: &mut B,
+        address: Word,
+        operation: fn(&mut CPU, Byte) -> Byte,
     ) {
-        cpu.y = cpu.y + operand1;
-        let new_operand1 = memory.get_byte(make_address(0x00, operand1));
-        let new_operand2 = memory.get_byte(make_address(0x00, operand2));
-        let data = memory.get_byte(make_address(new_operand1, new_operand2));
-        cpu.lda(data);
+        let data = memory.read(address);
+        let result = operation(self, data);
+        memory.write(address, result);
     }
 
-    fn handle_ldx_immediate(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.ldx(operand1);
+    // the value an instruction works on, immediate data or the byte at the address
+    fn read<B: Bus>(&self, memory: &mut B, args: InstructionArgs) -> Byte {
+        match args {
+            InstructionArgs::OneByte(data) => data,
+            InstructionArgs::Address(address) => memory.read(address),
+            _ => self.acc,
+        }
     }
 
-    fn handle_ldy_immediate(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.ldy(operand1);
+    // read-modify-write on memory, or on the accumulator in accumulator mode
+    fn modify<B: Bus>(
+        &mut self,
+        memory: &mut B,
+        args: InstructionArgs,
+        operation: fn(&mut CPU, Byte) -> Byte,
+    ) {
+        match args {
+            InstructionArgs::Address(address) => self.read_modify_write(memory, address, operation),
+            _ => self.acc = operation(self, self.acc),
+        }
     }
 
     // loads byte into accumulator
     pub fn lda(&mut self, data: Byte) {
-        self.prgmctr += 2;
         self.acc = data;
-        self.status.n = true;
-        self.status.z = true;
+        self.status.set_zn(self.acc);
     }
     // direct
-    pub fn push(&mut self, memory: &mut MEMORY, data: Byte) {
-        memory.set_byte(self.stkptr, data);
-        self.stkptr -= 1;
-        //self.stkptr = xextend(data);
+    pub fn push<B: Bus>(&mut self, memory: &mut B, data: Byte) {
+        memory.write(self.stack_address(), data);
+        if self.stkptr == 0x00 && self.stack_diagnostics {
+            self.stack_event = Some(StackEvent::Overflow { pc: self.prgmctr });
+        }
+        self.stkptr = self.stkptr.wrapping_sub(1);
+    }
+
+    pub fn pull<B: Bus>(&mut self, memory: &mut B) -> Byte {
+        if self.stkptr == 0xFF && self.stack_diagnostics {
+            self.stack_event = Some(StackEvent::Underflow { pc: self.prgmctr });
+        }
+        self.stkptr = self.stkptr.wrapping_add(1);
+        memory.read(self.stack_address())
     }
 
-    pub fn pull(&mut self, memory: &mut MEMORY) -> Byte {
-        self.stkptr += 1;
-        memory.get_byte(self.stkptr)
+    // the stack always lives in page one
+    pub fn stack_address(&self) -> Word {
+        STACK_LOW | self.stkptr as Word
     }
 
-    fn handle_jmp_absolute(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        let address = make_address(operand1, operand2);
-        cpu.jmp(address);
+    // the last wraparound seen since this was called, if stack_diagnostics is on
+    pub fn take_stack_event(&mut self) -> Option<StackEvent> {
+        self.stack_event.take()
     }
 
     pub fn jmp(&mut self, data: Word) {
         self.prgmctr = data;
     }
 
-    fn handle_pha(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.pha(memory);
+    // jump to subroutine, pushes the address of the last operand byte
+    pub fn jsr<B: Bus>(&mut self, memory: &mut B, address: Word) {
+        let (high, low) = split_address(self.prgmctr.wrapping_sub(1));
+        self.push(memory, high);
+        self.push(memory, low);
+        self.prgmctr = address;
     }
 
-    // push accumulator
-    pub fn pha(&mut self, memory: &mut MEMORY) {
-        self.prgmctr += 1;
-        self.push(memory, self.acc)
+    // return from subroutine
+    pub fn rts<B: Bus>(&mut self, memory: &mut B) {
+        let low = self.pull(memory);
+        let high = self.pull(memory);
+        self.prgmctr = make_address(high, low).wrapping_add(1);
     }
 
-    fn handle_pla(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.pla(memory);
-    }
-
-    // pull accumulator
-    pub fn pla(&mut self, memory: &mut MEMORY) -> Byte {
-        self.acc = self.pull(memory);
-        self.prgmctr += 1;
-        self.acc
+    // return from interrupt
+    pub fn rti<B: Bus>(&mut self, memory: &mut B) {
+        self.status = Status::from_byte(self.pull(memory));
+        let low = self.pull(memory);
+        let high = self.pull(memory);
+        self.prgmctr = make_address(high, low);
     }
 
-    fn handle_nop(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.nop();
+    // branches relative to the next instruction when the condition holds
+    // a taken branch costs a cycle, and another if it lands in a different page
+    pub fn branch(&mut self, condition: bool, offset: Byte) {
+        if condition {
+            let target = self.prgmctr.wrapping_add(offset as i8 as Word);
+            self.extra_cycles += 1;
+            if (target & 0xFF00) != (self.prgmctr & 0xFF00) {
+                self.extra_cycles += 1;
+            }
+            self.prgmctr = target;
+        }
     }
 
-    // no operation
-    pub fn nop(&mut self) {
-        self.prgmctr += 1;
+    // push accumulator
+    pub fn pha<B: Bus>(&mut self, memory: &mut B) {
+        self.push(memory, self.acc)
     }
 
-    fn handle_php(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.php(operand1);
+    // pull accumulator
+    pub fn pla<B: Bus>(&mut self, memory: &mut B) -> Byte {
+        self.acc = self.pull(memory);
+        self.status.set_zn(self.acc);
+        self.acc
     }
 
-    // push processor status
-    pub fn php(&mut self, data: Byte) {
-        self.status.n = (data & 0b0000_0001) != 0;
-        self.status.v = (data & 0b0000_0010) != 0;
-        self.status.u = (data & 0b0000_0100) != 0;
-        self.status.b = (data & 0b0000_1000) != 0;
-        self.status.d = (data & 0b0001_0000) != 0;
-        self.status.i = (data & 0b0010_0000) != 0;
-        self.status.z = (data & 0b0100_0000) != 0;
-        self.status.c = (data & 0b1000_0000) != 0;
-        self.prgmctr += 1;
-    }
+    // no operation
+    pub fn nop(&mut self) {}
 
-    fn handle_plp(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.plp();
+    // push processor status, the pushed copy has the break bit set
+    pub fn php<B: Bus>(&mut self, memory: &mut B) {
+        self.push(memory, self.status.to_byte() | Status::BREAK);
     }
 
     // pull processor status
-    pub fn plp(&mut self) -> Byte {
-        self.status.to_byte()
-    }
-
-    fn handle_txs(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.txs();
+    pub fn plp<B: Bus>(&mut self, memory: &mut B) -> Byte {
+        let data = self.pull(memory);
+        self.status = Status::from_byte(data);
+        data
     }
 
     // transfer accumulator to x
     pub fn txs(&mut self) {
-        self.stkptr = xextend(self.x);
-    }
-
-    fn handle_tsx(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.tsx();
+        self.stkptr = self.x;
     }
 
     // transfer stack pointer to x
     pub fn tsx(&mut self) {
-        self.prgmctr += 1;
-        self.x = split_address(self.stkptr).1;
-    }
-
-    fn handle_tax(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.tax();
+        self.x = self.stkptr;
+        self.status.set_zn(self.x);
     }
 
     // transfer accumulator to x
     pub fn tax(&mut self) {
-        self.prgmctr += 1;
         self.x = self.acc;
-    }
-
-    fn handle_txa(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.txa();
+        self.status.set_zn(self.x);
     }
 
     // transfer x to accumulator
     pub fn txa(&mut self) {
-        self.prgmctr += 1;
         self.acc = self.x;
+        self.status.set_zn(self.acc);
     }
 
     // transfer y to accumulator
     pub fn tya(&mut self) {
-        self.prgmctr += 1;
         self.acc = self.y;
-    }
-
-    fn handle_tya(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.tya();
+        self.status.set_zn(self.acc);
     }
 
     // transfer accumulator to y
     pub fn tay(&mut self) {
-        self.prgmctr += 1;
         self.y = self.acc;
+        self.status.set_zn(self.y);
     }
     // decrement y
 
-    fn handle_dey(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.dey();
-    }
-
     pub fn dey(&mut self) {
-        self.prgmctr += 1;
-        self.y -= 1;
-    }
-
-    fn handle_iny(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.iny();
+        self.y = self.y.wrapping_sub(1);
+        self.status.set_zn(self.y);
     }
 
     // increment y
     pub fn iny(&mut self) {
-        self.prgmctr += 1;
-        self.y += 1;
-    }
-
-    fn handle_inx(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.inx();
+        self.y = self.y.wrapping_add(1);
+        self.status.set_zn(self.y);
     }
 
     // increment x
     pub fn inx(&mut self) {
-        self.prgmctr += 1;
-        self.x += 1;
-    }
-
-    fn handle_dex(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.dex();
+        self.x = self.x.wrapping_add(1);
+        self.status.set_zn(self.x);
     }
 
     // decrement x
     pub fn dex(&mut self) {
-        self.prgmctr += 1;
-        self.x -= 1;
+        self.x = self.x.wrapping_sub(1);
+        self.status.set_zn(self.x);
+    }
+
+    // increment memory, returns the new value
+    pub fn inc(&mut self, data: Byte) -> Byte {
+        let result = data.wrapping_add(1);
+        self.status.set_zn(result);
+        result
     }
 
-    fn handle_brk(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.brk();
+    // decrement memory, returns the new value
+    pub fn dec(&mut self, data: Byte) -> Byte {
+        let result = data.wrapping_sub(1);
+        self.status.set_zn(result);
+        result
     }
 
-    // brk
-    pub fn brk(&mut self) {
-        self.prgmctr += 1;
-        self.status.b = true;
+    // brk, skips the padding byte after the opcode and vectors like an irq
+    pub fn brk<B: Bus>(&mut self, memory: &mut B) {
+        self.prgmctr = self.prgmctr.wrapping_add(1);
+        self.interrupt(memory, IRQ_VECTOR, true);
     }
 
     // load x
     pub fn ldx(&mut self, data: Byte) {
-        self.status.n = true;
-        self.status.z = true;
         self.x = data;
-        self.prgmctr += 2;
+        self.status.set_zn(self.x);
     }
     // load y
     pub fn ldy(&mut self, data: Byte) {
-        self.status.n = true;
-        self.status.z = true;
         self.y = data;
-        self.prgmctr += 2;
+        self.status.set_zn(self.y);
     }
 
-    fn handle_stx(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.stx(memory);
+    // store x
+    pub fn stx<B: Bus>(&mut self, memory: &mut B, address: Word) {
+        memory.write(address, self.x);
     }
 
-    fn handle_sty(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.sty(memory);
+    // store y
+    pub fn sty<B: Bus>(&mut self, memory: &mut B, address: Word) {
+        memory.write(address, self.y);
     }
 
-    // store x 0x86
-    pub fn stx(&mut self, memory: &mut MEMORY) {
-        memory.set_byte(self.prgmctr, self.x);
-        self.prgmctr += 2;
+    // store accumulator
+    pub fn sta<B: Bus>(&mut self, memory: &mut B, address: Word) {
+        memory.write(address, self.acc);
     }
 
-    //store y 0x84
-    pub fn sty(&mut self, memory: &mut MEMORY) {
-        memory.set_byte(self.prgmctr, self.y);
-        self.prgmctr += 2;
+    // store zero
+    pub fn stz<B: Bus>(&mut self, memory: &mut B, address: Word) {
+        memory.write(address, 0x00);
     }
 
-    fn handle_sta(cpu: &mut CPU, memory: &mut MEMORY, operand1: Byte, operand2: Byte) {
-        cpu.sta(memory);
+    // push x
+    pub fn phx<B: Bus>(&mut self, memory: &mut B) {
+        self.push(memory, self.x)
     }
 
-    pub fn sta(&mut self, memory: &mut MEMORY) {
-        memory.set_byte(self.prgmctr, self.acc);
+    // push y
+    pub fn phy<B: Bus>(&mut self, memory: &mut B) {
+        self.push(memory, self.y)
     }
 
-    pub fn execute_table(&mut self, m: &mut MEMORY) -> Option<Byte> {
-        let instruction = m.get_byte(self.prgmctr);
-        let operand1 = m.get_byte(self.prgmctr + 1);
-        let operand2 = m.get_byte(self.prgmctr + 2);
+    // pull x
+    pub fn plx<B: Bus>(&mut self, memory: &mut B) -> Byte {
+        self.x = self.pull(memory);
+        self.status.set_zn(self.x);
+        self.x
+    }
+
+    // pull y
+    pub fn ply<B: Bus>(&mut self, memory: &mut B) -> Byte {
+        self.y = self.pull(memory);
+        self.status.set_zn(self.y);
+        self.y
+    }
 
-        if let Some(handler) = CPU::INSTRUCTION_TABLE[instruction as usize] {
-            handler(self, m, operand1, operand2);
+    // test and set bits, returns the new value
+    pub fn tsb(&mut self, data: Byte) -> Byte {
+        self.status.z = (self.acc & data) == 0;
+        data | self.acc
+    }
 
-            None
+    // test and reset bits, returns the new value
+    pub fn trb(&mut self, data: Byte) -> Byte {
+        self.status.z = (self.acc & data) == 0;
+        data & !self.acc
+    }
+
+    fn decimal(&self) -> bool {
+        self.status.d && self.decimal_mode != DecimalMode::Disabled
+    }
+
+    // add with carry
+    pub fn adc(&mut self, data: Byte) {
+        if self.decimal() {
+            self.adc_decimal(data);
         } else {
-            None
+            self.adc_binary(data);
         }
     }
 
-    // executes and returms an option of the data depending on the instruction
-    pub fn execute(&mut self, m: &mut MEMORY) -> Option<Byte> {
-        let instruction = m.get_byte(self.prgmctr);
-        let operand1 = m.get_byte(self.prgmctr + 1);
-        let operand2 = m.get_byte(self.prgmctr + 2);
-        match instruction {
-            // lda block
-            // lda immediate
-            0xA9 => {
-                self.lda(operand1);
+    // subtract with borrow, the carry flag is the inverted borrow
+    pub fn sbc(&mut self, data: Byte) {
+        if self.decimal() {
+            self.sbc_decimal(data);
+        } else {
+            self.adc_binary(!data);
+        }
+    }
 
-                None
-            }
-            // lda absolute
-            0xAD => {
-                let concat_byte = make_address(operand1, operand2);
-                let data = m.get_byte(concat_byte);
+    fn adc_binary(&mut self, data: Byte) {
+        let sum = self.acc as Word + data as Word + self.status.c as Word;
+        self.status.set_add(self.acc, data, sum);
+        self.acc = sum as Byte;
+    }
 
-                self.lda(data);
-                None
-            }
-            // lda x indexed
-            0xBD => {
-                let concat_byte = make_address(operand1, operand2) + self.x as u16;
-                let data = m.get_byte(concat_byte);
+    // follows the sequences in bruce clark's decimal mode tutorial on 6502.org
+    fn adc_decimal(&mut self, data: Byte) {
+        if self.decimal_mode == DecimalMode::Cmos {
+            self.extra_cycles += 1;
+        }
+        let carry = self.status.c as i16;
+        let (acc, data16) = (self.acc as i16, data as i16);
 
-                self.lda(data);
-                None
-            }
-            // lda y indexed
-            0xB9 => {
-                let concat_byte = make_address(operand1, operand2) + self.y as u16;
-                let data = m.get_byte(concat_byte);
+        let mut low = (acc & 0x0F) + (data16 & 0x0F) + carry;
+        if low >= 0x0A {
+            low = ((low + 0x06) & 0x0F) + 0x10;
+        }
+        let mut sum = (acc & 0xF0) + (data16 & 0xF0) + low;
 
-                self.lda(data);
-                None
-            }
-            // lda zp
-            0xA5 => {
-                let data = m.get_byte(make_address(0x00, operand1));
+        // n and v are taken from the high nibble add before it is corrected
+        let signed = (self.acc as i8 as i16 & !0x0F) + (data as i8 as i16 & !0x0F) + low;
+        self.status.n = (signed & 0x80) != 0;
+        self.status.v = !(-128..=127).contains(&signed);
 
-                self.lda(data);
-                None
-            }
-            // lda zpx
-            0xB5 => {
-                let data = m.get_byte(make_address(0x00, operand1) + self.x as u16);
+        if sum >= 0xA0 {
+            sum += 0x60;
+        }
+        self.status.c = sum >= 0x100;
 
-                None
-            }
-            // lda x zp indexed indirect
-            0xA1 => {
-                self.x = self.x + operand1;
-                let new_operand1 = m.get_byte(make_address(0x00, self.x));
-                let new_operand2 = m.get_byte(make_address(0x00, operand2));
-                let data = m.get_byte(make_address(new_operand1, new_operand2));
-                self.lda(data);
+        let binary = self.acc.wrapping_add(data).wrapping_add(carry as Byte);
+        self.acc = sum as Byte;
+        match self.decimal_mode {
+            DecimalMode::Cmos => self.status.set_zn(self.acc),
+            _ => self.status.z = binary == 0,
+        }
+    }
 
-                None
+    fn sbc_decimal(&mut self, data: Byte) {
+        if self.decimal_mode == DecimalMode::Cmos {
+            self.extra_cycles += 1;
+        }
+        let borrow = 1 - self.status.c as i16;
+        let (acc, data16) = (self.acc as i16, data as i16);
+        let low = (acc & 0x0F) - (data16 & 0x0F) - borrow;
+
+        // carry and v always follow the binary subtraction
+        let sum = self.acc as Word + !data as Word + self.status.c as Word;
+        self.status.set_add(self.acc, !data, sum);
+
+        let result = match self.decimal_mode {
+            DecimalMode::Cmos => {
+                let mut result = acc - data16 - borrow;
+                if result < 0 {
+                    result -= 0x60;
+                }
+                if low < 0 {
+                    result -= 0x06;
+                }
+                result
+            }
+            _ => {
+                let mut low = low;
+                if low < 0 {
+                    low = ((low - 0x06) & 0x0F) - 0x10;
+                }
+                let mut result = (acc & 0xF0) - (data16 & 0xF0) + low;
+                if result < 0 {
+                    result -= 0x60;
+                }
+                result
             }
+        };
+        self.acc = result as Byte;
+        if self.decimal_mode == DecimalMode::Cmos {
+            self.status.set_zn(self.acc);
+        }
+    }
 
-            // lda y zp indirect indexed
-            0xB1 => {
-                self.y = self.y + operand1;
-                let new_operand1 = m.get_byte(make_address(0x00, operand1));
-                let new_operand2 = m.get_byte(make_address(0x00, operand2));
-                let data = m.get_byte(make_address(new_operand1, new_operand2));
-                self.lda(data);
+    // logical and
+    pub fn and(&mut self, data: Byte) {
+        self.acc &= data;
+        self.status.set_zn(self.acc);
+    }
 
-                None
-            }
-            // ldx
-            0xA2 => {
-                self.ldx(operand1);
-                None
-            }
-            // ldy
-            0xA0 => {
-                self.ldy(operand1);
+    // logical inclusive or
+    pub fn ora(&mut self, data: Byte) {
+        self.acc |= data;
+        self.status.set_zn(self.acc);
+    }
 
-                None
-            }
-            // jump
-            0x4C => {
-                self.jmp(make_address(operand1, operand2));
+    // logical exclusive or
+    pub fn eor(&mut self, data: Byte) {
+        self.acc ^= data;
+        self.status.set_zn(self.acc);
+    }
 
-                None
-            }
-            // pha
-            0x48 => {
-                self.pha(m);
-                None
-            }
-            // pla
-            0x68 => {
-                self.pla(m);
-                Some(self.acc)
-            }
-            // txs
-            0x9A => {
-                self.txs();
-                None
-            }
-            // tsx
-            0xBA => {
-                self.tsx();
-                None
-            }
-            // nop
-            0xEA => {
-                self.nop();
-                None
-            }
-            // php
-            0x08 => {
-                self.php(operand1);
-                None
-            }
-            // plp
-            0x28 => Some(self.plp()),
-            // tax
-            0xAA => {
-                self.tax();
-                None
-            }
-            // txa
-            0x8A => {
-                self.txa();
-                None
-            }
-            // dex
-            0xCA => {
-                self.dex();
-                None
-            }
-            // inx
-            0xE8 => {
-                self.inx();
-                None
-            }
-            // tay
-            0xA8 => {
-                self.tay();
-                None
+    // bit test, n and v are copied from the operand
+    pub fn bit(&mut self, data: Byte) {
+        self.status.z = (self.acc & data) == 0;
+        self.status.n = (data & 0b1000_0000) != 0;
+        self.status.v = (data & 0b0100_0000) != 0;
+    }
+
+    // bit test immediate only touches z
+    pub fn bit_immediate(&mut self, data: Byte) {
+        self.status.z = (self.acc & data) == 0;
+    }
+
+    // compare accumulator
+    pub fn cmp(&mut self, data: Byte) {
+        self.status.set_compare(self.acc, data);
+    }
+
+    // compare x
+    pub fn cpx(&mut self, data: Byte) {
+        self.status.set_compare(self.x, data);
+    }
+
+    // compare y
+    pub fn cpy(&mut self, data: Byte) {
+        self.status.set_compare(self.y, data);
+    }
+
+    // arithmetic shift left, returns the shifted value
+    pub fn asl(&mut self, data: Byte) -> Byte {
+        let result = data << 1;
+        self.status.c = (data & 0b1000_0000) != 0;
+        self.status.set_zn(result);
+        result
+    }
+
+    // logical shift right, returns the shifted value
+    pub fn lsr(&mut self, data: Byte) -> Byte {
+        let result = data >> 1;
+        self.status.c = (data & 0b0000_0001) != 0;
+        self.status.set_zn(result);
+        result
+    }
+
+    // rotate left through carry, returns the rotated value
+    pub fn rol(&mut self, data: Byte) -> Byte {
+        let result = (data << 1) | self.status.c as Byte;
+        self.status.c = (data & 0b1000_0000) != 0;
+        self.status.set_zn(result);
+        result
+    }
+
+    // rotate right through carry, returns the rotated value
+    pub fn ror(&mut self, data: Byte) -> Byte {
+        let result = (data >> 1) | ((self.status.c as Byte) << 7);
+        self.status.c = (data & 0b0000_0001) != 0;
+        self.status.set_zn(result);
+        result
+    }
+
+    // stops the cpu until the next reset, leaving the pc on the opcode
+    pub fn halt(&mut self) {
+        self.prgmctr = self.prgmctr.wrapping_sub(1);
+        self.halted = true;
+    }
+
+    // lda and ldx at once
+    pub fn lax(&mut self, data: Byte) {
+        self.lda(data);
+        self.x = data;
+    }
+
+    // asl then ora, returns the shifted value
+    pub fn slo(&mut self, data: Byte) -> Byte {
+        let result = self.asl(data);
+        self.ora(result);
+        result
+    }
+
+    // rol then and, returns the rotated value
+    pub fn rla(&mut self, data: Byte) -> Byte {
+        let result = self.rol(data);
+        self.and(result);
+        result
+    }
+
+    // lsr then eor, returns the shifted value
+    pub fn sre(&mut self, data: Byte) -> Byte {
+        let result = self.lsr(data);
+        self.eor(result);
+        result
+    }
+
+    // ror then adc, returns the rotated value
+    pub fn rra(&mut self, data: Byte) -> Byte {
+        let result = self.ror(data);
+        self.adc(result);
+        result
+    }
+
+    // dec then cmp, returns the decremented value
+    pub fn dcp(&mut self, data: Byte) -> Byte {
+        let result = data.wrapping_sub(1);
+        self.cmp(result);
+        result
+    }
+
+    // inc then sbc, returns the incremented value
+    pub fn isc(&mut self, data: Byte) -> Byte {
+        let result = data.wrapping_add(1);
+        self.sbc(result);
+        result
+    }
+
+    // and immediate, carry is copied from bit 7
+    pub fn anc(&mut self, data: Byte) {
+        self.and(data);
+        self.status.c = self.status.n;
+    }
+
+    // and immediate then lsr a
+    pub fn alr(&mut self, data: Byte) {
+        self.and(data);
+        self.acc = self.lsr(self.acc);
+    }
+
+    // and immediate then ror a, with carry and overflow taken from bits 6 and 5
+    // in decimal mode the adder's bcd fixup is applied to each nibble
+    pub fn arr(&mut self, data: Byte) {
+        let and = self.acc & data;
+        let mut result = (and >> 1) | ((self.status.c as Byte) << 7);
+        self.status.set_zn(result);
+
+        if self.decimal() {
+            self.status.v = ((and ^ result) & 0b0100_0000) != 0;
+            if (and & 0x0F) + (and & 0x01) > 0x05 {
+                result = (result & 0xF0) | (result.wrapping_add(0x06) & 0x0F);
             }
-            // tya
-            0x98 => {
-                self.tya();
-                None
+            self.status.c = (and as Word + (and & 0x10) as Word) & 0x1F0 > 0x50;
+            if self.status.c {
+                result = result.wrapping_add(0x60);
             }
-            // dey
-            0x88 => {
-                self.dey();
+        } else {
+            self.status.c = (result & 0b0100_0000) != 0;
+            self.status.v = ((result >> 6) ^ (result >> 5)) & 0x01 != 0;
+        }
+        self.acc = result;
+    }
+
+    // x = (a & x) - immediate, carry and flags like a compare
+    pub fn sbx(&mut self, data: Byte) {
+        let and = self.acc & self.x;
+        self.status.set_compare(and, data);
+        self.x = and.wrapping_sub(data);
+    }
+
+    // unstable, depends on the chip through xaa_magic
+    pub fn xaa(&mut self, data: Byte) {
+        self.acc = (self.acc | self.unstable.xaa_magic) & self.x & data;
+        self.status.set_zn(self.acc);
+    }
+
+    // unstable, depends on the chip through lxa_magic
+    pub fn lxa(&mut self, data: Byte) {
+        self.lax((self.acc | self.unstable.lxa_magic) & data);
+    }
+
+    // a, x and the stack pointer all get memory and the stack pointer
+    pub fn las(&mut self, data: Byte) {
+        self.stkptr &= data;
+        self.lax(self.stkptr);
+    }
+
+    // the store half of sha, shx, shy and tas
+    fn store_high<B: Bus>(&mut self, memory: &mut B, address: Word, data: Byte) {
+        let (high, low) = split_address(address);
+        // the high byte of the base address, before indexing carried into it
+        let base = high.wrapping_sub(self.page_crossed as Byte);
+        let value = match self.unstable.sh_and_high {
+            true => data & base.wrapping_add(1),
+            false => data,
+        };
+        let address = match self.page_crossed && self.unstable.sh_page_cross {
+            true => make_address(value, low),
+            false => address,
+        };
+        memory.write(address, value);
+    }
+
+    // clear carry
+    pub fn clc(&mut self) {
+        self.status.c = false;
+    }
+
+    // set carry
+    pub fn sec(&mut self) {
+        self.status.c = true;
+    }
+
+    // clear interrupt disable
+    pub fn cli(&mut self) {
+        self.status.i = false;
+    }
+
+    // set interrupt disable
+    pub fn sei(&mut self) {
+        self.status.i = true;
+    }
+
+    // clear overflow
+    pub fn clv(&mut self) {
+        self.status.v = false;
+    }
+
+    // clear decimal
+    pub fn cld(&mut self) {
+        self.status.d = false;
+    }
+
+    // set decimal
+    pub fn sed(&mut self) {
+        self.status.d = true;
+    }
+
+    // executes and returms an option of the data depending on the instruction
+    // illegal opcodes are handled by the policy, an error is kept in fault
+    pub fn execute<B: Bus>(&mut self, m: &mut B) -> Option<Byte> {
+        match self.try_execute(m) {
+            Ok(data) => data,
+            Err(error) => {
+                self.fault = Some(error);
                 None
             }
-            // iny
-            0xC8 => {
-                self.iny();
-                None
+        }
+    }
+
+    // execute, but reports illegal opcodes under the halt and error policies
+    pub fn try_execute<B: Bus>(&mut self, m: &mut B) -> Result<Option<Byte>, ExecError> {
+        if self.halted {
+            return Ok(None);
+        }
+
+        if self.poll_interrupts(m) {
+            self.clock(m, 7);
+            return Ok(None);
+        }
+
+        self.page_crossed = false;
+        self.extra_cycles = 0;
+
+        let instruction = self.fetch_byte(m);
+        let opcode = opcode(self.variant, instruction);
+
+        // the 65c02 documents its spare opcodes as nops so only the nmos part has illegal ones
+        let illegal = opcode.illegal && self.variant == Variant::Nmos && !self.undocumented;
+        if illegal {
+            self.illegal_opcode(instruction)?;
+        }
+
+        let args = self.resolve(m, opcode.mode);
+        let data = match illegal {
+            true => None,
+            false => self.execute_with_args(m, opcode.mnemonic, args),
+        };
+
+        self.clock(m, self.instruction_cycles(opcode));
+        Ok(data)
+    }
+
+    // counts the cycles and lets the devices on the bus catch up
+    fn clock<B: Bus>(&mut self, m: &mut B, cycles: u32) {
+        self.cycles += cycles as u64;
+        m.tick(cycles);
+    }
+
+    // applies the illegal opcode policy, an error leaves the pc on the opcode
+    fn illegal_opcode(&mut self, instruction: Byte) -> Result<(), ExecError> {
+        let error = ExecError::IllegalOpcode {
+            pc: self.prgmctr.wrapping_sub(1),
+            opcode: instruction,
+        };
+        match &mut self.illegal_opcodes {
+            IllegalOpcodePolicy::Nop => Ok(()),
+            IllegalOpcodePolicy::Callback(callback) => {
+                callback(&error);
+                Ok(())
             }
-            0x86 => {
-                self.stx(m);
-                None
+            IllegalOpcodePolicy::Halt => {
+                self.halt();
+                self.fault = Some(error);
+                Err(error)
             }
-            0x84 => {
-                self.sty(m);
-                None
+            IllegalOpcodePolicy::Error => {
+                self.prgmctr = self.prgmctr.wrapping_sub(1);
+                Err(error)
             }
+        }
+    }
 
-            0x85 => {
-                self.sta(m);
-                None
-            }
+    // cycles taken by the instruction that just ran
+    fn instruction_cycles(&self, opcode: &Opcode) -> u32 {
+        let page = self.page_crossed && opcode.page_penalty(self.variant);
+        opcode.cycles as u32 + page as u32 + self.extra_cycles
+    }
 
-            0x00 => {
-                self.brk();
-                None
-            }
+    // runs one instruction or interrupt and returns how many cycles it took
+    pub fn step<B: Bus>(&mut self, m: &mut B) -> u32 {
+        let start = self.cycles;
+        self.execute(m);
+        (self.cycles - start) as u32
+    }
 
-            _ => None,
+    // runs whole instructions until at least the given number of cycles have passed,
+    // returns the cycles actually run which can overshoot by part of an instruction
+    // or fall short if the cpu halts or stops on an illegal opcode, see fault
+    pub fn run_for_cycles<B: Bus>(&mut self, m: &mut B, cycles: u64) -> u64 {
+        let start = self.cycles;
+        while self.cycles - start < cycles && !self.halted {
+            // the error policy leaves the pc on the opcode, going round again never ends
+            if let Err(error) = self.try_execute(m) {
+                self.fault = Some(error);
+                break;
+            }
         }
+        self.cycles - start
     }
+
     fn set_ctr(&mut self, value: Word) {
         self.prgmctr = value;
     }
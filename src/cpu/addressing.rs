@@ -0,0 +1,160 @@
+// effective address resolution for every addressing mode
+//
+// operands are little endian, zero page modes wrap within page zero, everything
+// else wraps at 0xFFFF, and indexing notes page crossings for the cycle counter
+use super::{make_address, split_address, Variant, CPU};
+use crate::bus::Bus;
+use crate::opcodes::Mode;
+use crate::{Byte, Word};
+
+// what an addressing mode resolves to before the instruction runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum InstructionArgs {
+    None,                // implied and accumulator
+    OneByte(Byte),       // immediate data or a branch offset
+    TwoByte(Byte, Byte), // zero page address and branch offset for bbr and bbs
+    Address(Word),       // effective address
+}
+
+impl InstructionArgs {
+    pub(super) fn byte(self) -> Byte {
+        match self {
+            InstructionArgs::OneByte(byte) => byte,
+            _ => unreachable!("{:?} has no immediate byte", self),
+        }
+    }
+
+    pub(super) fn address(self) -> Word {
+        match self {
+            InstructionArgs::Address(address) => address,
+            _ => unreachable!("{:?} has no effective address", self),
+        }
+    }
+}
+
+impl CPU {
+    // fetches the operand bytes of an addressing mode and resolves them
+    pub(super) fn resolve<B: Bus>(&mut self, memory: &mut B, mode: Mode) -> InstructionArgs {
+        match mode {
+            Mode::Implied | Mode::Accumulator => InstructionArgs::None,
+            Mode::Immediate | Mode::Relative => InstructionArgs::OneByte(self.fetch_byte(memory)),
+            Mode::ZeroPageRelative => {
+                let address = self.fetch_byte(memory);
+                InstructionArgs::TwoByte(address, self.fetch_byte(memory))
+            }
+            Mode::ZeroPage => InstructionArgs::Address(self.zero_page(memory)),
+            Mode::ZeroPageX => InstructionArgs::Address(self.zero_page_x(memory)),
+            Mode::ZeroPageY => InstructionArgs::Address(self.zero_page_y(memory)),
+            Mode::Absolute => InstructionArgs::Address(self.absolute(memory)),
+            Mode::AbsoluteX => InstructionArgs::Address(self.absolute_x(memory)),
+            Mode::AbsoluteY => InstructionArgs::Address(self.absolute_y(memory)),
+            Mode::Indirect => InstructionArgs::Address(self.indirect(memory)),
+            Mode::IndexedIndirect => InstructionArgs::Address(self.indexed_indirect(memory)),
+            Mode::IndirectIndexed => InstructionArgs::Address(self.indirect_indexed(memory)),
+            Mode::ZeroPageIndirect => InstructionArgs::Address(self.zero_page_indirect(memory)),
+            Mode::AbsoluteIndexedIndirect => {
+                InstructionArgs::Address(self.absolute_indexed_indirect(memory))
+            }
+        }
+    }
+
+    // reads a little endian word such as a vector
+    pub(super) fn read_word<B: Bus>(memory: &mut B, address: Word) -> Word {
+        let low = memory.read(address);
+        let high = memory.read(address.wrapping_add(1));
+        make_address(high, low)
+    }
+
+    // reads the byte at the program counter and steps past it
+    pub(super) fn fetch_byte<B: Bus>(&mut self, memory: &mut B) -> Byte {
+        let data = memory.read(self.prgmctr);
+        self.prgmctr = self.prgmctr.wrapping_add(1);
+        data
+    }
+
+    // operands are stored low byte first
+    fn fetch_word<B: Bus>(&mut self, memory: &mut B) -> Word {
+        let low = self.fetch_byte(memory);
+        let high = self.fetch_byte(memory);
+        make_address(high, low)
+    }
+
+    // reads a little endian pointer out of zero page, wrapping at 0xFF
+    fn read_zero_page_word<B: Bus>(memory: &mut B, pointer: Byte) -> Word {
+        let low = memory.read(make_address(0x00, pointer));
+        let high = memory.read(make_address(0x00, pointer.wrapping_add(1)));
+        make_address(high, low)
+    }
+
+    fn zero_page<B: Bus>(&mut self, memory: &mut B) -> Word {
+        make_address(0x00, self.fetch_byte(memory))
+    }
+
+    fn zero_page_x<B: Bus>(&mut self, memory: &mut B) -> Word {
+        make_address(0x00, self.fetch_byte(memory).wrapping_add(self.x))
+    }
+
+    fn zero_page_y<B: Bus>(&mut self, memory: &mut B) -> Word {
+        make_address(0x00, self.fetch_byte(memory).wrapping_add(self.y))
+    }
+
+    fn absolute<B: Bus>(&mut self, memory: &mut B) -> Word {
+        self.fetch_word(memory)
+    }
+
+    fn absolute_x<B: Bus>(&mut self, memory: &mut B) -> Word {
+        let base = self.fetch_word(memory);
+        self.index(base, self.x)
+    }
+
+    fn absolute_y<B: Bus>(&mut self, memory: &mut B) -> Word {
+        let base = self.fetch_word(memory);
+        self.index(base, self.y)
+    }
+
+    // adds an index register to a base address and notes any page crossing
+    fn index(&mut self, base: Word, index: Byte) -> Word {
+        let address = base.wrapping_add(index as Word);
+        self.page_crossed = (base & 0xFF00) != (address & 0xFF00);
+        address
+    }
+
+    // (zp,x)
+    fn indexed_indirect<B: Bus>(&mut self, memory: &mut B) -> Word {
+        let pointer = self.fetch_byte(memory).wrapping_add(self.x);
+        CPU::read_zero_page_word(memory, pointer)
+    }
+
+    // (zp),y
+    fn indirect_indexed<B: Bus>(&mut self, memory: &mut B) -> Word {
+        let pointer = self.fetch_byte(memory);
+        let base = CPU::read_zero_page_word(memory, pointer);
+        self.index(base, self.y)
+    }
+
+    // jmp (abs), the nmos part fetches the high byte without carrying into the page
+    fn indirect<B: Bus>(&mut self, memory: &mut B) -> Word {
+        let pointer = self.fetch_word(memory);
+        let (page, offset) = split_address(pointer);
+        let low = memory.read(pointer);
+        let high = match self.variant {
+            Variant::Nmos => memory.read(make_address(page, offset.wrapping_add(1))),
+            Variant::Cmos => memory.read(pointer.wrapping_add(1)),
+        };
+        make_address(high, low)
+    }
+
+    // (zp), 65c02 only
+    fn zero_page_indirect<B: Bus>(&mut self, memory: &mut B) -> Word {
+        let pointer = self.fetch_byte(memory);
+        CPU::read_zero_page_word(memory, pointer)
+    }
+
+    // jmp (abs,x), 65c02 only
+    fn absolute_indexed_indirect<B: Bus>(&mut self, memory: &mut B) -> Word {
+        let pointer = self.fetch_word(memory).wrapping_add(self.x as Word);
+        let low = memory.read(pointer);
+        let high = memory.read(pointer.wrapping_add(1));
+        make_address(high, low)
+    }
+}
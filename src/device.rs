@@ -0,0 +1,161 @@
+// memory mapped hardware, a device answers for a range of addresses, runs off the cpu
+// clock and can pull the interrupt lines
+//
+// addresses handed to a device are offsets from the start of the range it is mapped at
+use std::any::Any;
+use std::collections::VecDeque;
+use std::io::{self, stdin, Read};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+use crate::{Byte, Word};
+
+pub mod acia;
+pub mod hd44780;
+pub mod pia;
+pub mod riot;
+pub mod rriot;
+pub mod via;
+
+// hands a device back as its own type, see MemoryMap::device
+pub trait AsAny: Any {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub trait Device: AsAny {
+    fn name(&self) -> &str;
+    // a read the cpu makes, reading a register often clears a flag
+    fn read(&mut self, offset: Word) -> Byte {
+        self.peek(offset)
+    }
+    fn write(&mut self, offset: Word, value: Byte);
+    // a read without side effects, for debuggers and the disassembler
+    fn peek(&self, offset: Word) -> Byte;
+    // called once for every cpu clock cycle
+    fn tick(&mut self) {}
+    // whether the device is holding the irq line, it stays held until serviced
+    fn irq(&self) -> bool {
+        false
+    }
+    // whether the device is holding the nmi line, only becoming held triggers an nmi
+    fn nmi(&self) -> bool {
+        false
+    }
+}
+
+// reads bytes on a thread of their own so the cpu never waits on the host
+pub fn spawn_reader<R: Read + Send + 'static>(mut reader: R) -> Receiver<Byte> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = [0; 256];
+        while let Ok(length @ 1..) = reader.read(&mut buffer) {
+            if buffer[..length]
+                .iter()
+                .any(|byte| sender.send(*byte).is_err())
+            {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+// bytes from the host, queued with push or arriving on a channel
+pub struct HostInput {
+    queued: VecDeque<Byte>,
+    receiver: Option<Receiver<Byte>>,
+    stdin: bool, // polls the shared stdin reader instead
+}
+
+impl HostInput {
+    pub fn new() -> Self {
+        Self {
+            queued: VecDeque::new(),
+            receiver: None,
+            stdin: false,
+        }
+    }
+
+    pub fn stdin() -> Self {
+        Self {
+            stdin: true,
+            ..Self::new()
+        }
+    }
+
+    pub fn connect(&mut self, receiver: Receiver<Byte>) {
+        self.receiver = Some(receiver);
+        self.stdin = false;
+    }
+
+    pub fn push(&mut self, bytes: &[Byte]) {
+        self.queued.extend(bytes);
+    }
+
+    // goes ahead of anything already queued
+    pub fn push_front(&mut self, byte: Byte) {
+        self.queued.push_front(byte);
+    }
+
+    // queued bytes first, then whatever the channel has without waiting
+    pub fn poll(&mut self) -> Option<Byte> {
+        match self.queued.pop_front() {
+            Some(byte) => Some(byte),
+            None if self.stdin => stdin_reader()
+                .get_or_insert_with(|| spawn_reader(stdin()))
+                .try_recv()
+                .ok(),
+            None => self.receiver.as_ref()?.try_recv().ok(),
+        }
+    }
+}
+
+impl Default for HostInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// whether a handshake line going from before to after is the edge the control
+// register is waiting for
+pub fn edge(before: bool, after: bool, positive: bool) -> bool {
+    before != after && after == positive
+}
+
+// stdin gets one reader for good, started by the first device to poll it, the bytes
+// wait in its channel until a device or the repl asks for them
+static STDIN: Mutex<Option<Receiver<Byte>>> = Mutex::new(None);
+
+fn stdin_reader() -> MutexGuard<'static, Option<Receiver<Byte>>> {
+    STDIN.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// a line for the repl, through the shared reader once a device has started it so the
+// repl and a machine that has stopped running never split stdin between them
+pub fn read_stdin_line(line: &mut String) -> io::Result<usize> {
+    let reader = stdin_reader();
+    let Some(receiver) = reader.as_ref() else {
+        return stdin().read_line(line);
+    };
+    let mut bytes = Vec::new();
+    while let Ok(byte) = receiver.recv() {
+        bytes.push(byte);
+        if byte == b'\n' {
+            break;
+        }
+    }
+    line.push_str(&String::from_utf8_lossy(&bytes));
+    Ok(bytes.len())
+}
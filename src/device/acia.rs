@@ -0,0 +1,280 @@
+// mos 6551 asynchronous communications interface adapter, a serial port bridged to
+// the host, transmitted bytes go to a writer and received bytes come from a channel
+//
+// each character takes as many cpu cycles as the programmed baud rate says it would
+use std::io::{stdout, Write};
+use std::sync::mpsc::Receiver;
+
+use crate::device::{Device, HostInput};
+use crate::{Byte, Word};
+
+// register offsets
+pub const DATA: Word = 0x0;
+pub const STATUS: Word = 0x1; // writing it is a programmed reset
+pub const COMMAND: Word = 0x2;
+pub const CONTROL: Word = 0x3;
+
+// baud rates selected by the low four bits of control, 0 is the 16x external clock
+// which is taken to be the usual 1.8432MHz crystal
+const BAUD_RATES: [f64; 16] = [
+    115200.0, 50.0, 75.0, 109.92, 134.58, 150.0, 300.0, 600.0, 1200.0, 1800.0, 2400.0, 3600.0,
+    4800.0, 7200.0, 9600.0, 19200.0,
+];
+
+pub struct Acia {
+    pub clock_hz: u32, // the cpu clock the baud rate is counted against
+    // host line endings, received \n becomes \r and transmitted \r becomes \n
+    pub translate_newlines: bool,
+
+    status: Byte,
+    command: Byte,
+    control: Byte,
+    rx_data: Byte,
+    tx_data: Option<Byte>, // written but not yet in the shift register
+
+    rx_shift: Option<Byte>, // the character on the wire and the cycles it has left
+    rx_cycles: u32,
+    tx_shift: Option<Byte>,
+    tx_cycles: u32,
+    last_sent_cr: bool,
+
+    input: HostInput,
+    output: Option<Box<dyn Write>>,
+    transmitted: Vec<Byte>, // kept until take_transmitted when there is no output
+}
+
+impl Acia {
+    // status bits
+    pub const PARITY_ERROR: Byte = 0x01;
+    pub const FRAMING_ERROR: Byte = 0x02;
+    pub const OVERRUN: Byte = 0x04;
+    pub const RDRF: Byte = 0x08; // receive data register full
+    pub const TDRE: Byte = 0x10; // transmit data register empty
+    pub const DCD: Byte = 0x20; // active low, carrier always detected
+    pub const DSR: Byte = 0x40; // active low, always ready
+    pub const IRQ: Byte = 0x80;
+
+    // not connected to anything, feed it with receive and read it with take_transmitted
+    pub fn new() -> Self {
+        Self {
+            clock_hz: 1_000_000,
+            translate_newlines: false,
+            status: Acia::TDRE,
+            command: 0x00,
+            control: 0x00,
+            rx_data: 0x00,
+            tx_data: None,
+            rx_shift: None,
+            rx_cycles: 0,
+            tx_shift: None,
+            tx_cycles: 0,
+            last_sent_cr: false,
+            input: HostInput::new(),
+            output: None,
+            transmitted: Vec::new(),
+        }
+    }
+
+    // talks to the host terminal, stdin is line buffered so characters arrive once
+    // enter is pressed
+    pub fn stdio() -> Self {
+        let mut acia = Self::new();
+        acia.input = HostInput::stdin();
+        acia.translate_newlines = true;
+        acia.connect_output(Box::new(stdout()));
+        acia
+    }
+
+    pub fn connect_input(&mut self, input: Receiver<Byte>) {
+        self.input.connect(input);
+    }
+
+    // e.g. a file or the slave side of a pty
+    pub fn connect_output(&mut self, output: Box<dyn Write>) {
+        self.output = Some(output);
+    }
+
+    // queues bytes as if they had arrived on the wire
+    pub fn receive(&mut self, bytes: &[Byte]) {
+        self.input.push(bytes);
+    }
+
+    // bytes transmitted since the last call when no output is connected
+    pub fn take_transmitted(&mut self) -> Vec<Byte> {
+        std::mem::take(&mut self.transmitted)
+    }
+
+    pub fn reset(&mut self) {
+        self.status = Acia::TDRE;
+        self.command = 0x00;
+        self.control = 0x00;
+        self.tx_data = None;
+    }
+
+    // cycles to move one character with the start, data, parity and stop bits
+    fn frame_cycles(&self) -> u32 {
+        let data = 8 - ((self.control >> 5) & 0x03) as u32;
+        let parity = ((self.command >> 5) & 0x01) as u32;
+        let stop = 1 + (self.control >> 7) as u32;
+        let baud = BAUD_RATES[(self.control & 0x0F) as usize];
+        ((1 + data + parity + stop) as f64 * self.clock_hz as f64 / baud) as u32
+    }
+
+    // dtr low turns the receiver and interrupts off
+    fn receiver_enabled(&self) -> bool {
+        self.command & 0x01 != 0
+    }
+
+    fn transmit_control(&self) -> Byte {
+        (self.command >> 2) & 0x03
+    }
+
+    fn echo(&self) -> bool {
+        self.command & 0x10 != 0 && self.transmit_control() == 0x00
+    }
+
+    fn next_received(&mut self) -> Option<Byte> {
+        let byte = self.input.poll()?;
+        match (self.translate_newlines, byte) {
+            (true, b'\n') => Some(b'\r'),
+            _ => Some(byte),
+        }
+    }
+
+    fn send(&mut self, byte: Byte) {
+        let byte = match self.translate_newlines {
+            true => {
+                // \r\n only needs the one newline
+                let skip = byte == b'\n' && self.last_sent_cr;
+                self.last_sent_cr = byte == b'\r';
+                if skip {
+                    return;
+                }
+                if byte == b'\r' {
+                    b'\n'
+                } else {
+                    byte
+                }
+            }
+            false => byte,
+        };
+        match self.output.as_mut() {
+            Some(output) => {
+                let _ = output.write_all(&[byte]);
+                let _ = output.flush();
+            }
+            None => self.transmitted.push(byte),
+        }
+    }
+
+    fn tick_receiver(&mut self) {
+        if self.rx_shift.is_none() {
+            if !self.receiver_enabled() {
+                return;
+            }
+            self.rx_shift = self.next_received();
+            self.rx_cycles = self.frame_cycles();
+            return;
+        }
+
+        self.rx_cycles = self.rx_cycles.saturating_sub(1);
+        if self.rx_cycles > 0 {
+            return;
+        }
+
+        let byte = self.rx_shift.take().unwrap_or_default();
+        // a character arriving before the last was read is lost
+        if self.status & Acia::RDRF != 0 {
+            self.status |= Acia::OVERRUN;
+            return;
+        }
+        self.rx_data = byte;
+        self.status |= Acia::RDRF;
+        if self.command & 0x02 == 0 {
+            self.status |= Acia::IRQ;
+        }
+        if self.echo() {
+            self.send(byte);
+        }
+    }
+
+    fn tick_transmitter(&mut self) {
+        if self.tx_shift.is_some() {
+            self.tx_cycles = self.tx_cycles.saturating_sub(1);
+            if self.tx_cycles == 0 {
+                let byte = self.tx_shift.take().unwrap_or_default();
+                self.send(byte);
+            }
+            return;
+        }
+
+        // rts high is the transmitter turned off
+        if self.transmit_control() == 0x00 {
+            return;
+        }
+        if let Some(byte) = self.tx_data.take() {
+            self.tx_shift = Some(byte);
+            self.tx_cycles = self.frame_cycles();
+            self.status |= Acia::TDRE;
+            if self.transmit_control() == 0x01 {
+                self.status |= Acia::IRQ;
+            }
+        }
+    }
+}
+
+impl Device for Acia {
+    fn name(&self) -> &str {
+        "6551 acia"
+    }
+
+    fn read(&mut self, offset: Word) -> Byte {
+        let value = self.peek(offset);
+        match offset & 0x03 {
+            DATA => self.status &= !(Acia::RDRF | Acia::OVERRUN | Acia::PARITY_ERROR),
+            STATUS => self.status &= !Acia::IRQ,
+            _ => {}
+        }
+        value
+    }
+
+    fn write(&mut self, offset: Word, value: Byte) {
+        match offset & 0x03 {
+            DATA => {
+                self.tx_data = Some(value);
+                self.status &= !Acia::TDRE;
+            }
+            // programmed reset keeps the parity settings and the control register
+            STATUS => {
+                self.command &= 0xE0;
+                self.status &= !Acia::OVERRUN;
+            }
+            COMMAND => self.command = value,
+            _ => self.control = value,
+        }
+    }
+
+    fn peek(&self, offset: Word) -> Byte {
+        match offset & 0x03 {
+            DATA => self.rx_data,
+            STATUS => self.status,
+            COMMAND => self.command,
+            _ => self.control,
+        }
+    }
+
+    fn tick(&mut self) {
+        self.tick_receiver();
+        self.tick_transmitter();
+    }
+
+    fn irq(&self) -> bool {
+        self.status & Acia::IRQ != 0 && self.receiver_enabled()
+    }
+}
+
+impl Default for Acia {
+    fn default() -> Self {
+        Self::new()
+    }
+}
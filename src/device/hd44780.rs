@@ -0,0 +1,309 @@
+// hitachi hd44780 character lcd controller, not a bus device but something hung off a
+// port, it is driven a pin at a time through update
+//
+// transfers happen on the falling edge of e, reads put data on the bus while e is high
+use crate::Byte;
+
+pub struct Hd44780 {
+    pub columns: usize,
+    pub clock_hz: u64, // the cpu clock, for turning the busy times into cycles
+    ddram: [Byte; 128],
+    cgram: [Byte; 64],
+    address: Byte,
+    cgram_selected: bool, // data goes to cgram rather than ddram
+    increment: bool,
+    shift_display: bool, // entry mode shifts the display instead of moving the cursor
+    display_on: bool,
+    cursor_on: bool,
+    blink_on: bool,
+    eight_bit: bool,
+    two_lines: bool,
+    shift: usize,         // how far the display has been shifted left
+    busy: u64,            // cycles until the busy flag drops
+    nibble: Option<Byte>, // the high half of a 4 bit write
+    low_nibble: bool,     // the next 4 bit read is the low half
+    e: bool,
+    rs: bool,
+    rw: bool,
+    changed: bool,
+}
+
+impl Hd44780 {
+    // instruction times in microseconds
+    const CLEAR_MICROS: u64 = 1520;
+    const INSTRUCTION_MICROS: u64 = 37;
+    const DATA_MICROS: u64 = 41;
+
+    // powers up in 8 bit mode with one line and the display off
+    pub fn new(columns: usize) -> Self {
+        Self {
+            columns,
+            clock_hz: 1_000_000,
+            ddram: [b' '; 128],
+            cgram: [0x00; 64],
+            address: 0x00,
+            cgram_selected: false,
+            increment: true,
+            shift_display: false,
+            display_on: false,
+            cursor_on: false,
+            blink_on: false,
+            eight_bit: true,
+            two_lines: false,
+            shift: 0,
+            busy: 0,
+            nibble: None,
+            low_nibble: false,
+            e: false,
+            rs: false,
+            rw: false,
+            changed: true,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.busy = self.busy.saturating_sub(1);
+    }
+
+    pub fn busy(&self) -> bool {
+        self.busy > 0
+    }
+
+    pub fn eight_bit(&self) -> bool {
+        self.eight_bit
+    }
+
+    // the register select, read/write and enable lines and d7-d0, in 4 bit mode only
+    // d7-d4 are looked at
+    pub fn update(&mut self, rs: bool, rw: bool, e: bool, data: Byte) {
+        let falling = self.e && !e;
+        if falling {
+            match self.rw {
+                false => self.transfer(self.rs, data),
+                true => self.read_done(self.rs),
+            }
+        }
+        self.e = e;
+        self.rs = rs;
+        self.rw = rw;
+    }
+
+    // what the lcd is driving onto d7-d0, only while e is high for a read
+    pub fn output(&self) -> Option<Byte> {
+        if !(self.e && self.rw) {
+            return None;
+        }
+        let value = match self.rs {
+            true => self.read_memory(),
+            false => (self.busy() as Byte) << 7 | self.address,
+        };
+        match (self.eight_bit, self.low_nibble) {
+            (true, _) => Some(value),
+            (false, false) => Some(value & 0xF0),
+            (false, true) => Some(value << 4),
+        }
+    }
+
+    // the visible characters, one string per line
+    pub fn lines(&self) -> Vec<String> {
+        let rows: &[Byte] = match self.two_lines {
+            true => &[0x00, 0x40],
+            false => &[0x00],
+        };
+        rows.iter()
+            .map(|start| {
+                (0..self.columns)
+                    .map(|column| {
+                        let address = *start + self.line_offset(column + self.shift);
+                        character(self.ddram[address as usize])
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // the cursor's line and column on screen when it is showing
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        if !(self.display_on && (self.cursor_on || self.blink_on)) || self.cgram_selected {
+            return None;
+        }
+        let (line, offset) = match (self.two_lines, self.address >= 0x40) {
+            (true, true) => (1, self.address - 0x40),
+            _ => (0, self.address),
+        };
+        let length = self.line_length() as usize;
+        let column = (offset as usize + length - self.shift % length) % length;
+        (column < self.columns).then_some((line, column))
+    }
+
+    pub fn display_on(&self) -> bool {
+        self.display_on
+    }
+
+    // whether anything visible changed since the last call
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    fn line_length(&self) -> Byte {
+        match self.two_lines {
+            true => 40,
+            false => 80,
+        }
+    }
+
+    fn line_offset(&self, column: usize) -> Byte {
+        (column % self.line_length() as usize) as Byte
+    }
+
+    fn transfer(&mut self, rs: bool, data: Byte) {
+        let value = match (self.eight_bit, self.nibble.take()) {
+            (true, _) => data,
+            (false, None) => {
+                self.nibble = Some(data & 0xF0);
+                return;
+            }
+            (false, Some(high)) => high | data >> 4,
+        };
+        // the lcd gets on with it even while busy, only the busy flag says to wait
+        match rs {
+            true => self.write_memory(value),
+            false => self.instruction(value),
+        }
+        self.changed = true;
+    }
+
+    fn read_done(&mut self, rs: bool) {
+        if !self.eight_bit {
+            self.low_nibble = !self.low_nibble;
+            if self.low_nibble {
+                return;
+            }
+        }
+        if rs {
+            self.advance();
+            self.busy = self.cycles(Hd44780::DATA_MICROS);
+        }
+    }
+
+    fn cycles(&self, micros: u64) -> u64 {
+        micros * self.clock_hz / 1_000_000
+    }
+
+    fn instruction(&mut self, value: Byte) {
+        let mut micros = Hd44780::INSTRUCTION_MICROS;
+        match value.leading_zeros() {
+            7 => {
+                self.ddram = [b' '; 128];
+                self.address = 0x00;
+                self.cgram_selected = false;
+                self.increment = true;
+                self.shift = 0;
+                micros = Hd44780::CLEAR_MICROS;
+            }
+            6 => {
+                self.address = 0x00;
+                self.cgram_selected = false;
+                self.shift = 0;
+                micros = Hd44780::CLEAR_MICROS;
+            }
+            5 => {
+                self.increment = value & 0x02 != 0;
+                self.shift_display = value & 0x01 != 0;
+            }
+            4 => {
+                self.display_on = value & 0x04 != 0;
+                self.cursor_on = value & 0x02 != 0;
+                self.blink_on = value & 0x01 != 0;
+            }
+            3 => match value & 0x08 {
+                0 => self.move_cursor(value & 0x04 != 0),
+                _ => self.shift_by(value & 0x04 != 0),
+            },
+            2 => {
+                self.eight_bit = value & 0x10 != 0;
+                self.two_lines = value & 0x08 != 0;
+                self.nibble = None;
+                self.low_nibble = false;
+            }
+            1 => {
+                self.address = value & 0x3F;
+                self.cgram_selected = true;
+            }
+            0 => {
+                self.address = value & 0x7F;
+                self.cgram_selected = false;
+            }
+            // a zero byte does nothing
+            _ => {}
+        }
+        self.busy = self.cycles(micros);
+    }
+
+    fn write_memory(&mut self, value: Byte) {
+        match self.cgram_selected {
+            true => self.cgram[(self.address & 0x3F) as usize] = value,
+            false => self.ddram[self.address as usize] = value,
+        }
+        self.advance();
+        if self.shift_display && !self.cgram_selected {
+            self.shift_by(!self.increment);
+        }
+        self.busy = self.cycles(Hd44780::DATA_MICROS);
+    }
+
+    fn read_memory(&self) -> Byte {
+        match self.cgram_selected {
+            true => self.cgram[(self.address & 0x3F) as usize],
+            false => self.ddram[self.address as usize],
+        }
+    }
+
+    // moves the address counter on after a data read or write
+    fn advance(&mut self) {
+        self.move_cursor(self.increment);
+    }
+
+    fn move_cursor(&mut self, right: bool) {
+        if self.cgram_selected {
+            self.address = match right {
+                true => self.address.wrapping_add(1),
+                false => self.address.wrapping_sub(1),
+            } & 0x3F;
+            return;
+        }
+
+        // two line mode skips from the end of the first line to the start of the second
+        self.address = match (self.two_lines, right, self.address) {
+            (true, true, 0x27) => 0x40,
+            (true, true, 0x67) => 0x00,
+            (true, false, 0x00) => 0x67,
+            (true, false, 0x40) => 0x27,
+            (false, true, 0x4F) => 0x00,
+            (false, false, 0x00) => 0x4F,
+            (_, true, address) => (address + 1) & 0x7F,
+            (_, false, address) => address.wrapping_sub(1) & 0x7F,
+        };
+    }
+
+    // shifting left moves everything on screen one place left
+    fn shift_by(&mut self, right: bool) {
+        let length = self.line_length() as usize;
+        self.shift = match right {
+            true => (self.shift + length - 1) % length,
+            false => (self.shift + 1) % length,
+        };
+    }
+}
+
+// the a00 character rom, close enough for a terminal
+fn character(code: Byte) -> char {
+    match code {
+        0x00..=0x0F => '?', // custom characters from cgram
+        0x5C => '¥',
+        0x7E => '→',
+        0x7F => '←',
+        0x20..=0x7D => code as char,
+        _ => '?',
+    }
+}
@@ -0,0 +1,422 @@
+// mos 6520 / motorola 6820 and 6821 peripheral interface adapter, two 8 bit ports each
+// with a control register and two handshake lines
+//
+// four registers, the data and direction registers share an offset and bit 2 of the
+// control register picks between them
+use std::io::{stdout, Write};
+use std::sync::mpsc::Receiver;
+
+use crate::device::{edge, Device, HostInput};
+use crate::{Byte, Word};
+
+// register offsets
+pub const PORT_A: Word = 0x0; // ora or ddra
+pub const CRA: Word = 0x1;
+pub const PORT_B: Word = 0x2; // orb or ddrb
+pub const CRB: Word = 0x3;
+
+// host keystrokes presented on port a, each one strobes ca1 once the last was read
+pub struct Keyboard {
+    pub high_bit: bool, // sets bit 7 of every key, the apple i keyboard ties it high
+    pub uppercase: bool,
+    pub translate_newlines: bool, // \n becomes \r
+    pub backspace: Option<Byte>,  // what backspace and delete become
+    input: HostInput,
+}
+
+impl Keyboard {
+    // not connected to anything, feed it with press
+    pub fn new() -> Self {
+        Self {
+            high_bit: false,
+            uppercase: false,
+            translate_newlines: false,
+            backspace: None,
+            input: HostInput::new(),
+        }
+    }
+
+    // stdin is line buffered so keys arrive once enter is pressed
+    pub fn stdin() -> Self {
+        Self {
+            input: HostInput::stdin(),
+            translate_newlines: true,
+            ..Self::new()
+        }
+    }
+
+    pub fn connect_input(&mut self, input: Receiver<Byte>) {
+        self.input.connect(input);
+    }
+
+    pub fn press(&mut self, keys: &[Byte]) {
+        self.input.push(keys);
+    }
+
+    fn next_key(&mut self) -> Option<Byte> {
+        let mut key = self.input.poll()?;
+        if self.translate_newlines && key == b'\n' {
+            key = b'\r';
+        }
+        if let (Some(backspace), 0x08 | 0x7F) = (self.backspace, key) {
+            key = backspace;
+        }
+        if self.uppercase {
+            key = key.to_ascii_uppercase();
+        }
+        if self.high_bit {
+            key |= 0x80;
+        }
+        Some(key)
+    }
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// prints what is written to port b, holding pb7 high while it is busy with a character
+pub struct Display {
+    pub mask: Byte,               // the port b bits that make up a character
+    pub translate_newlines: bool, // \r becomes \n
+    pub uppercase: bool,
+    pub printable_only: bool, // drops control characters other than \r
+    pub busy_cycles: u32,     // how long each character takes to show
+    busy: u32,
+    output: Option<Box<dyn Write>>,
+    printed: Vec<Byte>,
+}
+
+impl Display {
+    // not connected to anything, read it with take_printed
+    pub fn new() -> Self {
+        Self {
+            mask: 0x7F,
+            translate_newlines: false,
+            uppercase: false,
+            printable_only: false,
+            busy_cycles: 0,
+            busy: 0,
+            output: None,
+            printed: Vec::new(),
+        }
+    }
+
+    pub fn stdout() -> Self {
+        let mut display = Self {
+            translate_newlines: true,
+            ..Self::new()
+        };
+        display.connect_output(Box::new(stdout()));
+        display
+    }
+
+    pub fn connect_output(&mut self, output: Box<dyn Write>) {
+        self.output = Some(output);
+    }
+
+    // characters printed since the last call when no output is connected
+    pub fn take_printed(&mut self) -> Vec<Byte> {
+        std::mem::take(&mut self.printed)
+    }
+
+    pub fn busy(&self) -> bool {
+        self.busy > 0
+    }
+
+    fn show(&mut self, value: Byte) {
+        let mut byte = value & self.mask;
+        // still takes its time even if nothing shows
+        self.busy = self.busy_cycles;
+        if self.printable_only && byte.is_ascii_control() && byte != b'\r' {
+            return;
+        }
+        if self.uppercase {
+            byte = byte.to_ascii_uppercase();
+        }
+        if self.translate_newlines && byte == b'\r' {
+            byte = b'\n';
+        }
+        match self.output.as_mut() {
+            Some(output) => {
+                let _ = output.write_all(&[byte]);
+                let _ = output.flush();
+            }
+            None => self.printed.push(byte),
+        }
+    }
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Pia {
+    pub irq_wired: bool, // whether irqa and irqb reach the cpu at all
+    ora: Byte,
+    ddra: Byte,
+    cra: Byte,
+    orb: Byte,
+    ddrb: Byte,
+    crb: Byte,
+    pa_pins: Byte, // what the outside world drives onto the port pins
+    pb_pins: Byte,
+
+    // control line levels, inputs as driven from outside, ca2 and cb2 also as outputs
+    ca1: bool,
+    ca2: bool,
+    cb1: bool,
+    cb2: bool,
+    ca2_out: bool,
+    cb2_out: bool,
+
+    keyboard: Option<Keyboard>,
+    display: Option<Display>,
+}
+
+impl Pia {
+    // control register bits
+    pub const C1_IRQ_ENABLE: Byte = 0x01;
+    pub const C1_POSITIVE: Byte = 0x02;
+    pub const DATA: Byte = 0x04; // the data register rather than the ddr
+    pub const C2_OUTPUT: Byte = 0x20;
+    pub const IRQ2: Byte = 0x40;
+    pub const IRQ1: Byte = 0x80;
+
+    pub fn new() -> Self {
+        Self {
+            irq_wired: true,
+            ora: 0x00,
+            ddra: 0x00,
+            cra: 0x00,
+            orb: 0x00,
+            ddrb: 0x00,
+            crb: 0x00,
+            pa_pins: 0xFF,
+            pb_pins: 0xFF,
+            ca1: true,
+            ca2: true,
+            cb1: true,
+            cb2: true,
+            ca2_out: true,
+            cb2_out: true,
+            keyboard: None,
+            display: None,
+        }
+    }
+
+    // port a keys and port b characters, the way the apple i wires them
+    pub fn attach_keyboard(&mut self, keyboard: Keyboard) {
+        self.keyboard = Some(keyboard);
+    }
+
+    pub fn attach_display(&mut self, display: Display) {
+        self.display = Some(display);
+    }
+
+    pub fn keyboard_mut(&mut self) -> Option<&mut Keyboard> {
+        self.keyboard.as_mut()
+    }
+
+    pub fn display_mut(&mut self) -> Option<&mut Display> {
+        self.display.as_mut()
+    }
+
+    pub fn reset(&mut self) {
+        self.ora = 0x00;
+        self.ddra = 0x00;
+        self.cra = 0x00;
+        self.orb = 0x00;
+        self.ddrb = 0x00;
+        self.crb = 0x00;
+        self.ca2_out = true;
+        self.cb2_out = true;
+    }
+
+    pub fn port_a(&self) -> Byte {
+        (self.ora & self.ddra) | (self.pa_pins & !self.ddra)
+    }
+
+    pub fn port_b(&self) -> Byte {
+        (self.orb & self.ddrb) | (self.pb_pins & !self.ddrb)
+    }
+
+    pub fn set_port_a(&mut self, value: Byte) {
+        self.pa_pins = value;
+    }
+
+    pub fn set_port_b(&mut self, value: Byte) {
+        self.pb_pins = value;
+    }
+
+    pub fn set_ca1(&mut self, level: bool) {
+        if edge(self.ca1, level, self.cra & Pia::C1_POSITIVE != 0) {
+            self.cra |= Pia::IRQ1;
+            // read handshake, the peripheral has new data
+            if self.cra & 0x38 == 0x20 {
+                self.ca2_out = true;
+            }
+        }
+        self.ca1 = level;
+    }
+
+    pub fn set_ca2(&mut self, level: bool) {
+        let input = self.cra & Pia::C2_OUTPUT == 0;
+        if input && edge(self.ca2, level, self.cra & 0x10 != 0) {
+            self.cra |= Pia::IRQ2;
+        }
+        self.ca2 = level;
+    }
+
+    pub fn set_cb1(&mut self, level: bool) {
+        if edge(self.cb1, level, self.crb & Pia::C1_POSITIVE != 0) {
+            self.crb |= Pia::IRQ1;
+            // write handshake, the peripheral has taken the data
+            if self.crb & 0x38 == 0x20 {
+                self.cb2_out = true;
+            }
+        }
+        self.cb1 = level;
+    }
+
+    pub fn set_cb2(&mut self, level: bool) {
+        let input = self.crb & Pia::C2_OUTPUT == 0;
+        if input && edge(self.cb2, level, self.crb & 0x10 != 0) {
+            self.crb |= Pia::IRQ2;
+        }
+        self.cb2 = level;
+    }
+
+    // the ca2 level, whether it is an input or driven by the pia
+    pub fn ca2(&self) -> bool {
+        line(self.cra, self.ca2, self.ca2_out)
+    }
+
+    pub fn cb2(&self) -> bool {
+        line(self.crb, self.cb2, self.cb2_out)
+    }
+
+    pub fn irq_a(&self) -> bool {
+        interrupting(self.cra)
+    }
+
+    pub fn irq_b(&self) -> bool {
+        interrupting(self.crb)
+    }
+
+    // a strobe that sets the ca1 flag whichever edge it is waiting for
+    fn strobe_ca1(&mut self) {
+        let level = self.ca1;
+        self.set_ca1(!level);
+        self.set_ca1(level);
+    }
+}
+
+// c2 as an input, a manual output or the handshake output
+fn line(control: Byte, input: bool, handshake: bool) -> bool {
+    match control & 0x38 {
+        0x30 => false,
+        0x38 => true,
+        0x20 | 0x28 => handshake,
+        _ => input,
+    }
+}
+
+fn interrupting(control: Byte) -> bool {
+    let c1 = control & Pia::IRQ1 != 0 && control & Pia::C1_IRQ_ENABLE != 0;
+    let c2 = control & Pia::IRQ2 != 0 && control & 0x28 == 0x08;
+    c1 || c2
+}
+
+impl Device for Pia {
+    fn name(&self) -> &str {
+        "6821 pia"
+    }
+
+    // reading a data register clears that side's flags, reading ora also runs the
+    // ca2 handshake
+    fn read(&mut self, offset: Word) -> Byte {
+        let value = self.peek(offset);
+        match offset & 0x03 {
+            PORT_A if self.cra & Pia::DATA != 0 => {
+                self.cra &= !(Pia::IRQ1 | Pia::IRQ2);
+                if self.cra & 0x30 == 0x20 {
+                    self.ca2_out = false;
+                }
+            }
+            PORT_B if self.crb & Pia::DATA != 0 => self.crb &= !(Pia::IRQ1 | Pia::IRQ2),
+            _ => {}
+        }
+        value
+    }
+
+    fn write(&mut self, offset: Word, value: Byte) {
+        match offset & 0x03 {
+            PORT_A if self.cra & Pia::DATA != 0 => self.ora = value,
+            PORT_A => self.ddra = value,
+            // the flags are read only
+            CRA => self.cra = (self.cra & 0xC0) | (value & 0x3F),
+            PORT_B if self.crb & Pia::DATA != 0 => {
+                self.orb = value;
+                if self.crb & 0x30 == 0x20 {
+                    self.cb2_out = false;
+                }
+                let character = self.port_b();
+                if let Some(display) = self.display.as_mut() {
+                    display.show(character);
+                }
+            }
+            PORT_B => self.ddrb = value,
+            _ => self.crb = (self.crb & 0xC0) | (value & 0x3F),
+        }
+    }
+
+    fn peek(&self, offset: Word) -> Byte {
+        match offset & 0x03 {
+            PORT_A if self.cra & Pia::DATA != 0 => self.port_a(),
+            PORT_A => self.ddra,
+            CRA => self.cra,
+            // output pins read back orb rather than the pin
+            PORT_B if self.crb & Pia::DATA != 0 => self.port_b(),
+            PORT_B => self.ddrb,
+            _ => self.crb,
+        }
+    }
+
+    fn tick(&mut self) {
+        // c2 control 101 pulses the line low for a single cycle
+        if self.cra & 0x38 == 0x28 {
+            self.ca2_out = true;
+        }
+        if self.crb & 0x38 == 0x28 {
+            self.cb2_out = true;
+        }
+
+        if self.cra & Pia::IRQ1 == 0 {
+            if let Some(key) = self.keyboard.as_mut().and_then(Keyboard::next_key) {
+                self.pa_pins = key;
+                self.strobe_ca1();
+            }
+        }
+
+        if let Some(display) = self.display.as_mut() {
+            display.busy = display.busy.saturating_sub(1);
+            let busy = (display.busy() as Byte) << 7;
+            self.pb_pins = (self.pb_pins & 0x7F) | busy;
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_wired && (self.irq_a() || self.irq_b())
+    }
+}
+
+impl Default for Pia {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,240 @@
+// mos 6532 ram, i/o and timer, 128 bytes of ram, two 8 bit ports, an interval timer
+// and an edge detector on pa7
+//
+// offsets follow the chip's pins, a0-a6 with the ram select line as bit 7, so the
+// ram is at 0x00-0x7F and the registers at 0x80-0x9F
+use crate::device::Device;
+use crate::{Byte, Word};
+
+pub const RAM: Word = 0x00;
+pub const IO: Word = 0x80;
+
+// register offsets from IO
+pub const DRA: Word = 0x00;
+pub const DDRA: Word = 0x01;
+pub const DRB: Word = 0x02;
+pub const DDRB: Word = 0x03;
+pub const INTIM: Word = 0x04; // read the timer, or write the pa7 edge control
+pub const INSTAT: Word = 0x05; // read the interrupt flags
+pub const TIM1T: Word = 0x14; // timer writes, one per prescale
+pub const TIM8T: Word = 0x15;
+pub const TIM64T: Word = 0x16;
+pub const T1024T: Word = 0x17;
+pub const ENABLE_IRQ: Word = 0x08; // added to the timer reads and writes
+
+// a port's output register, data direction and whatever is driving its input pins
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Port {
+    pub(super) output: Byte,
+    pub(super) ddr: Byte,
+    pub(super) pins: Byte,
+}
+
+impl Port {
+    pub(super) fn new() -> Self {
+        Self {
+            output: 0x00,
+            ddr: 0x00,
+            pins: 0xFF,
+        }
+    }
+
+    pub(super) fn levels(&self) -> Byte {
+        (self.output & self.ddr) | (self.pins & !self.ddr)
+    }
+}
+
+// counts down once every 1, 8, 64 or 1024 cycles, after running out it carries on
+// from 0xFF a cycle at a time until it is written again
+#[derive(Debug, Clone, Copy)]
+pub(super) struct IntervalTimer {
+    value: Byte,
+    prescale: u16,
+    divider: u16,
+    expired: bool,
+    pub(super) flag: bool,
+    pub(super) irq_enabled: bool,
+}
+
+impl IntervalTimer {
+    const PRESCALES: [u16; 4] = [1, 8, 64, 1024];
+
+    pub(super) fn new() -> Self {
+        Self {
+            value: 0xFF,
+            prescale: 1024,
+            divider: 1024,
+            expired: false,
+            flag: false,
+            irq_enabled: false,
+        }
+    }
+
+    // the low two address bits pick the prescale, a3 enables the interrupt
+    pub(super) fn write(&mut self, offset: Word, value: Byte) {
+        self.value = value;
+        self.prescale = IntervalTimer::PRESCALES[(offset & 0x03) as usize];
+        // the first count comes on the next cycle, running out n * prescale + 1 cycles on
+        self.divider = 1;
+        self.expired = false;
+        self.flag = false;
+        self.irq_enabled = offset & ENABLE_IRQ != 0;
+    }
+
+    pub(super) fn read(&mut self, offset: Word) -> Byte {
+        self.irq_enabled = offset & ENABLE_IRQ != 0;
+        self.flag = false;
+        self.value
+    }
+
+    pub(super) fn peek(&self) -> Byte {
+        self.value
+    }
+
+    pub(super) fn tick(&mut self) {
+        self.divider -= 1;
+        if self.divider > 0 {
+            return;
+        }
+
+        if self.value == 0 {
+            self.expired = true;
+            self.flag = true;
+        }
+        self.value = self.value.wrapping_sub(1);
+        self.divider = match self.expired {
+            true => 1,
+            false => self.prescale,
+        };
+    }
+
+    pub(super) fn irq(&self) -> bool {
+        self.flag && self.irq_enabled
+    }
+}
+
+pub struct Riot {
+    ram: [Byte; 128],
+    port_a: Port,
+    port_b: Port,
+    timer: IntervalTimer,
+    pa7_flag: bool,
+    pa7_irq_enabled: bool,
+    pa7_positive: bool, // the edge on pa7 that sets the flag
+}
+
+impl Riot {
+    // interrupt flag bits
+    pub const TIMER: Byte = 0x80;
+    pub const PA7: Byte = 0x40;
+
+    pub fn new() -> Self {
+        Self {
+            ram: [0x00; 128],
+            port_a: Port::new(),
+            port_b: Port::new(),
+            timer: IntervalTimer::new(),
+            pa7_flag: false,
+            pa7_irq_enabled: false,
+            pa7_positive: false,
+        }
+    }
+
+    pub fn port_a(&self) -> Byte {
+        self.port_a.levels()
+    }
+
+    pub fn port_b(&self) -> Byte {
+        self.port_b.levels()
+    }
+
+    pub fn set_port_a(&mut self, value: Byte) {
+        self.change_port_a(|port| port.pins = value);
+    }
+
+    pub fn set_port_b(&mut self, value: Byte) {
+        self.port_b.pins = value;
+    }
+
+    // changes port a, watching pa7 whether it is an input or an output
+    fn change_port_a(&mut self, change: impl FnOnce(&mut Port)) {
+        let before = self.port_a.levels() & 0x80 != 0;
+        change(&mut self.port_a);
+        let after = self.port_a.levels() & 0x80 != 0;
+        if before != after && after == self.pa7_positive {
+            self.pa7_flag = true;
+        }
+    }
+
+    fn flags(&self) -> Byte {
+        (self.timer.flag as Byte) << 7 | (self.pa7_flag as Byte) << 6
+    }
+}
+
+impl Device for Riot {
+    fn name(&self) -> &str {
+        "6532 riot"
+    }
+
+    fn read(&mut self, offset: Word) -> Byte {
+        let value = self.peek(offset);
+        if offset & IO != 0 && offset & 0x04 != 0 {
+            match offset & 0x01 {
+                0 => {
+                    self.timer.read(offset);
+                }
+                _ => self.pa7_flag = false,
+            }
+        }
+        value
+    }
+
+    fn write(&mut self, offset: Word, value: Byte) {
+        if offset & IO == 0 {
+            self.ram[(offset & 0x7F) as usize] = value;
+            return;
+        }
+
+        match (offset & 0x14, offset & 0x03) {
+            (0x00 | 0x10, 0x00) => self.change_port_a(|port| port.output = value),
+            (0x00 | 0x10, 0x01) => self.change_port_a(|port| port.ddr = value),
+            (0x00 | 0x10, 0x02) => self.port_b.output = value,
+            (0x00 | 0x10, _) => self.port_b.ddr = value,
+            (0x14, _) => self.timer.write(offset, value),
+            // a0 picks the edge and a1 enables its interrupt
+            _ => {
+                self.pa7_positive = offset & 0x01 != 0;
+                self.pa7_irq_enabled = offset & 0x02 != 0;
+            }
+        }
+    }
+
+    fn peek(&self, offset: Word) -> Byte {
+        if offset & IO == 0 {
+            return self.ram[(offset & 0x7F) as usize];
+        }
+
+        match (offset & 0x04, offset & 0x03) {
+            (0x00, 0x00) => self.port_a.levels(),
+            (0x00, 0x01) => self.port_a.ddr,
+            (0x00, 0x02) => self.port_b.levels(),
+            (0x00, _) => self.port_b.ddr,
+            (_, 0x00 | 0x02) => self.timer.peek(),
+            _ => self.flags(),
+        }
+    }
+
+    fn tick(&mut self) {
+        self.timer.tick();
+    }
+
+    fn irq(&self) -> bool {
+        self.timer.irq() || (self.pa7_flag && self.pa7_irq_enabled)
+    }
+}
+
+impl Default for Riot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,127 @@
+// mos 6530 rom, ram, i/o and timer, the 6532's mask programmed relative with 1K of
+// rom, 64 bytes of ram and no edge detector
+//
+// offsets put the rom at 0x000-0x3FF, the ram at 0x400-0x43F and the registers at
+// 0x440-0x44F, map_device_window places each part where the machine decodes it
+use crate::device::riot::{IntervalTimer, Port};
+use crate::device::Device;
+use crate::{Byte, Word};
+
+pub const ROM: Word = 0x000;
+pub const RAM: Word = 0x400;
+pub const IO: Word = 0x440;
+
+// register offsets from IO
+pub const PAD: Word = 0x0;
+pub const PADD: Word = 0x1;
+pub const PBD: Word = 0x2;
+pub const PBDD: Word = 0x3;
+pub const TIMER: Word = 0x4; // writes 0x4-0x7 pick the prescale, reads get the timer
+pub const FLAGS: Word = 0x7; // the timer flag in bit 7
+pub use crate::device::riot::ENABLE_IRQ;
+
+pub struct Rriot {
+    rom: [Byte; 1024],
+    ram: [Byte; 64],
+    port_a: Port,
+    port_b: Port,
+    timer: IntervalTimer,
+}
+
+impl Rriot {
+    pub fn new(rom: &[Byte]) -> Self {
+        let mut image = [0x00; 1024];
+        for (byte, rom) in image.iter_mut().zip(rom) {
+            *byte = *rom;
+        }
+        Self {
+            rom: image,
+            ram: [0x00; 64],
+            port_a: Port::new(),
+            port_b: Port::new(),
+            timer: IntervalTimer::new(),
+        }
+    }
+
+    pub fn port_a(&self) -> Byte {
+        self.port_a.levels()
+    }
+
+    pub fn port_b(&self) -> Byte {
+        self.port_b.levels()
+    }
+
+    // the port registers, for machines that need to see them as they are written
+    pub fn port_a_ddr(&self) -> Byte {
+        self.port_a.ddr
+    }
+
+    pub fn port_b_ddr(&self) -> Byte {
+        self.port_b.ddr
+    }
+
+    pub fn set_port_a(&mut self, value: Byte) {
+        self.port_a.pins = value;
+    }
+
+    pub fn set_port_b(&mut self, value: Byte) {
+        self.port_b.pins = value;
+    }
+}
+
+impl Device for Rriot {
+    fn name(&self) -> &str {
+        "6530 rriot"
+    }
+
+    fn read(&mut self, offset: Word) -> Byte {
+        let value = self.peek(offset);
+        let register = offset.wrapping_sub(IO) & 0x0F;
+        if offset >= IO && register & 0x05 == 0x04 {
+            self.timer.read(register);
+        }
+        value
+    }
+
+    fn write(&mut self, offset: Word, value: Byte) {
+        match offset {
+            ROM..=0x3FF => {}
+            RAM..=0x43F => self.ram[(offset - RAM) as usize] = value,
+            // a3 only matters to the timer
+            _ => match (offset - IO) & 0x0F {
+                register if register & 0x04 != 0 => self.timer.write(register, value),
+                register => match register & 0x03 {
+                    PAD => self.port_a.output = value,
+                    PADD => self.port_a.ddr = value,
+                    PBD => self.port_b.output = value,
+                    _ => self.port_b.ddr = value,
+                },
+            },
+        }
+    }
+
+    fn peek(&self, offset: Word) -> Byte {
+        match offset {
+            ROM..=0x3FF => self.rom[offset as usize],
+            RAM..=0x43F => self.ram[(offset - RAM) as usize],
+            _ => match (offset - IO) & 0x0F {
+                register if register & 0x05 == 0x04 => self.timer.peek(),
+                register if register & 0x05 == 0x05 => (self.timer.flag as Byte) << 7,
+                register => match register & 0x03 {
+                    PAD => self.port_a.levels(),
+                    PADD => self.port_a.ddr,
+                    PBD => self.port_b.levels(),
+                    _ => self.port_b.ddr,
+                },
+            },
+        }
+    }
+
+    fn tick(&mut self) {
+        self.timer.tick();
+    }
+
+    fn irq(&self) -> bool {
+        self.timer.irq()
+    }
+}
@@ -0,0 +1,479 @@
+// mos 6522 versatile interface adapter, two 8 bit ports with handshaking, two timers
+// and a shift register
+//
+// sixteen registers, repeated across however much space the via is mapped over
+use crate::device::{edge, Device};
+use crate::{Byte, Word};
+
+// register offsets
+pub const ORB: Word = 0x0;
+pub const ORA: Word = 0x1;
+pub const DDRB: Word = 0x2;
+pub const DDRA: Word = 0x3;
+pub const T1C_L: Word = 0x4;
+pub const T1C_H: Word = 0x5;
+pub const T1L_L: Word = 0x6;
+pub const T1L_H: Word = 0x7;
+pub const T2C_L: Word = 0x8;
+pub const T2C_H: Word = 0x9;
+pub const SR: Word = 0xA;
+pub const ACR: Word = 0xB;
+pub const PCR: Word = 0xC;
+pub const IFR: Word = 0xD;
+pub const IER: Word = 0xE;
+pub const ORA_NO_HANDSHAKE: Word = 0xF;
+
+pub struct Via {
+    orb: Byte,
+    ora: Byte,
+    ddrb: Byte,
+    ddra: Byte,
+    pa_pins: Byte, // what the outside world drives onto the port pins
+    pb_pins: Byte,
+    ira_latch: Byte, // port inputs latched by ca1 and cb1 when latching is on
+    irb_latch: Byte,
+
+    t1_counter: Word,
+    t1_latch: Word,
+    t1_armed: bool,  // one shot interrupt still to come
+    t1_reload: bool, // free run reload on the cycle after the underflow
+    pb7: bool,
+
+    t2_counter: Word,
+    t2_latch_low: Byte,
+    t2_armed: bool,
+
+    sr: Byte,
+    sr_shifts: Byte, // shifts left until the shift register interrupt
+    sr_out: bool,    // the last bit shifted out onto cb2
+
+    acr: Byte,
+    pcr: Byte,
+    ifr: Byte,
+    ier: Byte,
+
+    // control line levels, inputs as driven from outside, ca2 and cb2 also as outputs
+    ca1: bool,
+    ca2: bool,
+    cb1: bool,
+    cb2: bool,
+    ca2_out: bool,
+    cb2_out: bool,
+}
+
+impl Via {
+    // interrupt flag and enable bits
+    pub const CA2: Byte = 0x01;
+    pub const CA1: Byte = 0x02;
+    pub const SHIFT: Byte = 0x04;
+    pub const CB2: Byte = 0x08;
+    pub const CB1: Byte = 0x10;
+    pub const T2: Byte = 0x20;
+    pub const T1: Byte = 0x40;
+    pub const IRQ: Byte = 0x80;
+
+    pub fn new() -> Self {
+        Self {
+            orb: 0x00,
+            ora: 0x00,
+            ddrb: 0x00,
+            ddra: 0x00,
+            pa_pins: 0xFF,
+            pb_pins: 0xFF,
+            ira_latch: 0x00,
+            irb_latch: 0x00,
+            t1_counter: 0xFFFF,
+            t1_latch: 0xFFFF,
+            t1_armed: false,
+            t1_reload: false,
+            pb7: true,
+            t2_counter: 0xFFFF,
+            t2_latch_low: 0xFF,
+            t2_armed: false,
+            sr: 0x00,
+            sr_shifts: 0,
+            sr_out: true,
+            acr: 0x00,
+            pcr: 0x00,
+            ifr: 0x00,
+            ier: 0x00,
+            ca1: true,
+            ca2: true,
+            cb1: true,
+            cb2: true,
+            ca2_out: true,
+            cb2_out: true,
+        }
+    }
+
+    // the reset line clears the control and port registers but not the timers or the
+    // shift register
+    pub fn reset(&mut self) {
+        self.orb = 0x00;
+        self.ora = 0x00;
+        self.ddrb = 0x00;
+        self.ddra = 0x00;
+        self.acr = 0x00;
+        self.pcr = 0x00;
+        self.ifr = 0x00;
+        self.ier = 0x00;
+        self.t1_armed = false;
+        self.t2_armed = false;
+        self.sr_shifts = 0;
+        self.ca2_out = true;
+        self.cb2_out = true;
+    }
+
+    // the levels on the port a pins, outputs where the ddr says so and inputs elsewhere
+    pub fn port_a(&self) -> Byte {
+        (self.ora & self.ddra) | (self.pa_pins & !self.ddra)
+    }
+
+    // port b, with pb7 following timer 1 when acr bit 7 is set
+    pub fn port_b(&self) -> Byte {
+        let pins = (self.orb & self.ddrb) | (self.pb_pins & !self.ddrb);
+        match self.acr & 0x80 {
+            0 => pins,
+            _ => (pins & 0x7F) | ((self.pb7 as Byte) << 7),
+        }
+    }
+
+    // drives the input pins of port a
+    pub fn set_port_a(&mut self, value: Byte) {
+        self.pa_pins = value;
+    }
+
+    // drives the input pins of port b, a falling edge on pb6 counts down timer 2
+    // in pulse counting mode
+    pub fn set_port_b(&mut self, value: Byte) {
+        let before = self.port_b();
+        self.pb_pins = value;
+        let falling = before & !self.port_b() & 0x40 != 0;
+        if falling && self.acr & 0x20 != 0 {
+            self.t2_counter = self.t2_counter.wrapping_sub(1);
+            if self.t2_counter == 0 && self.t2_armed {
+                self.t2_armed = false;
+                self.ifr |= Via::T2;
+            }
+        }
+    }
+
+    pub fn set_ca1(&mut self, level: bool) {
+        if edge(self.ca1, level, self.pcr & 0x01 != 0) {
+            self.ifr |= Via::CA1;
+            if self.acr & 0x01 != 0 {
+                self.ira_latch = self.port_a();
+            }
+            // handshake mode, the peripheral has taken the data
+            if (self.pcr >> 1) & 0x07 == 0x04 {
+                self.ca2_out = true;
+            }
+        }
+        self.ca1 = level;
+    }
+
+    pub fn set_ca2(&mut self, level: bool) {
+        let input = self.pcr & 0x08 == 0;
+        if input && edge(self.ca2, level, self.pcr & 0x04 != 0) {
+            self.ifr |= Via::CA2;
+        }
+        self.ca2 = level;
+    }
+
+    // cb1 is also the shift clock in the external clock modes, data shifts in on
+    // the rising edge and out on the falling edge
+    pub fn set_cb1(&mut self, level: bool) {
+        if edge(self.cb1, level, self.pcr & 0x10 != 0) {
+            self.ifr |= Via::CB1;
+            if self.acr & 0x02 != 0 {
+                self.irb_latch = self.port_b();
+            }
+            if (self.pcr >> 5) & 0x07 == 0x04 {
+                self.cb2_out = true;
+            }
+        }
+        let external = (self.acr >> 2) & 0x03 == 0x03;
+        let shifting_out = self.acr & 0x10 != 0;
+        if external && level != self.cb1 && level != shifting_out {
+            self.shift();
+        }
+        self.cb1 = level;
+    }
+
+    pub fn set_cb2(&mut self, level: bool) {
+        let input = self.pcr & 0x80 == 0 && !self.shifting_out();
+        if input && edge(self.cb2, level, self.pcr & 0x40 != 0) {
+            self.ifr |= Via::CB2;
+        }
+        self.cb2 = level;
+    }
+
+    // the ca2 level, whether it is an input or driven by the via
+    pub fn ca2(&self) -> bool {
+        match (self.pcr >> 1) & 0x07 {
+            0x04 | 0x05 => self.ca2_out,
+            0x06 => false,
+            0x07 => true,
+            _ => self.ca2,
+        }
+    }
+
+    pub fn cb2(&self) -> bool {
+        if self.shifting_out() {
+            return self.sr_out;
+        }
+        match (self.pcr >> 5) & 0x07 {
+            0x04 | 0x05 => self.cb2_out,
+            0x06 => false,
+            0x07 => true,
+            _ => self.cb2,
+        }
+    }
+
+    fn shifting_out(&self) -> bool {
+        self.acr & 0x10 != 0
+    }
+
+    fn shift_mode(&self) -> Byte {
+        (self.acr >> 2) & 0x07
+    }
+
+    // ifr with bit 7 showing whether any enabled flag is set
+    fn flags(&self) -> Byte {
+        match self.ifr & self.ier & 0x7F {
+            0 => self.ifr,
+            _ => self.ifr | Via::IRQ,
+        }
+    }
+
+    fn input_a(&self) -> Byte {
+        match self.acr & 0x01 {
+            0 => self.port_a(),
+            _ => self.ira_latch,
+        }
+    }
+
+    // output pins read back the output register rather than the pin
+    fn input_b(&self) -> Byte {
+        let input = match self.acr & 0x02 {
+            0 => self.port_b(),
+            _ => self.irb_latch,
+        };
+        (self.orb & self.ddrb) | (input & !self.ddrb)
+    }
+
+    // reading or writing ora clears the ca flags and runs the ca2 handshake
+    fn port_a_access(&mut self) {
+        self.ifr &= !Via::CA1;
+        // the independent interrupt modes leave the ca2 flag alone
+        if self.pcr & 0x0A == 0x00 {
+            self.ifr &= !Via::CA2;
+        }
+        if let 0x04 | 0x05 = (self.pcr >> 1) & 0x07 {
+            self.ca2_out = false;
+        }
+    }
+
+    // same for orb, except the cb2 handshake only happens on writes
+    fn port_b_access(&mut self, write: bool) {
+        self.ifr &= !Via::CB1;
+        if self.pcr & 0xA0 == 0x00 {
+            self.ifr &= !Via::CB2;
+        }
+        if let (true, 0x04 | 0x05) = (write, (self.pcr >> 5) & 0x07) {
+            self.cb2_out = false;
+        }
+    }
+
+    // reading or writing the shift register starts another eight shifts
+    fn shift_access(&mut self) {
+        self.ifr &= !Via::SHIFT;
+        if self.shift_mode() != 0x00 {
+            self.sr_shifts = 8;
+        }
+    }
+
+    fn shift(&mut self) {
+        let mode = self.shift_mode();
+        // free running shift out keeps going without an interrupt
+        if mode == 0x00 || (self.sr_shifts == 0 && mode != 0x04) {
+            return;
+        }
+
+        if self.shifting_out() {
+            self.sr = self.sr.rotate_left(1);
+            self.sr_out = self.sr & 0x01 != 0;
+        } else {
+            self.sr = (self.sr << 1) | self.cb2 as Byte;
+        }
+
+        if mode != 0x04 {
+            self.sr_shifts -= 1;
+            if self.sr_shifts == 0 {
+                self.ifr |= Via::SHIFT;
+            }
+        }
+    }
+
+    fn tick_timer1(&mut self) {
+        if self.t1_reload {
+            self.t1_reload = false;
+            self.t1_counter = self.t1_latch;
+            return;
+        }
+
+        let underflow = self.t1_counter == 0;
+        self.t1_counter = self.t1_counter.wrapping_sub(1);
+        if !underflow {
+            return;
+        }
+
+        let free_run = self.acr & 0x40 != 0;
+        if free_run {
+            self.t1_reload = true;
+            self.ifr |= Via::T1;
+            self.pb7 = !self.pb7;
+        } else if self.t1_armed {
+            self.t1_armed = false;
+            self.ifr |= Via::T1;
+            self.pb7 = true;
+        }
+    }
+
+    fn tick_timer2(&mut self) {
+        match self.shift_mode() {
+            // the shift register runs off the low half of timer 2
+            0x01 | 0x04 | 0x05 => {
+                let low = self.t2_counter as Byte;
+                let low = match low {
+                    0 => {
+                        self.shift();
+                        self.t2_latch_low
+                    }
+                    _ => low - 1,
+                };
+                self.t2_counter = (self.t2_counter & 0xFF00) | low as Word;
+            }
+            _ if self.acr & 0x20 != 0 => {} // counting pulses on pb6
+            _ => {
+                let underflow = self.t2_counter == 0;
+                self.t2_counter = self.t2_counter.wrapping_sub(1);
+                if underflow && self.t2_armed {
+                    self.t2_armed = false;
+                    self.ifr |= Via::T2;
+                }
+            }
+        }
+    }
+}
+
+impl Device for Via {
+    fn name(&self) -> &str {
+        "6522 via"
+    }
+
+    fn read(&mut self, offset: Word) -> Byte {
+        let value = self.peek(offset);
+        match offset & 0x0F {
+            ORB => self.port_b_access(false),
+            ORA => self.port_a_access(),
+            T1C_L => self.ifr &= !Via::T1,
+            T2C_L => self.ifr &= !Via::T2,
+            SR => self.shift_access(),
+            _ => {}
+        }
+        value
+    }
+
+    fn write(&mut self, offset: Word, value: Byte) {
+        match offset & 0x0F {
+            ORB => {
+                self.orb = value;
+                self.port_b_access(true);
+            }
+            ORA => {
+                self.ora = value;
+                self.port_a_access();
+            }
+            DDRB => self.ddrb = value,
+            DDRA => self.ddra = value,
+            T1C_L | T1L_L => self.t1_latch = (self.t1_latch & 0xFF00) | value as Word,
+            T1C_H => {
+                self.t1_latch = (self.t1_latch & 0x00FF) | (value as Word) << 8;
+                self.t1_counter = self.t1_latch;
+                self.t1_armed = true;
+                self.t1_reload = false;
+                self.ifr &= !Via::T1;
+                self.pb7 = false;
+            }
+            T1L_H => {
+                self.t1_latch = (self.t1_latch & 0x00FF) | (value as Word) << 8;
+                self.ifr &= !Via::T1;
+            }
+            T2C_L => self.t2_latch_low = value,
+            T2C_H => {
+                self.t2_counter = (value as Word) << 8 | self.t2_latch_low as Word;
+                self.t2_armed = true;
+                self.ifr &= !Via::T2;
+            }
+            SR => {
+                self.sr = value;
+                self.shift_access();
+            }
+            ACR => self.acr = value,
+            PCR => self.pcr = value,
+            IFR => self.ifr &= !(value & 0x7F),
+            IER => match value & Via::IRQ {
+                0 => self.ier &= !value,
+                _ => self.ier |= value & 0x7F,
+            },
+            _ => self.ora = value,
+        }
+    }
+
+    fn peek(&self, offset: Word) -> Byte {
+        match offset & 0x0F {
+            ORB => self.input_b(),
+            ORA | ORA_NO_HANDSHAKE => self.input_a(),
+            DDRB => self.ddrb,
+            DDRA => self.ddra,
+            T1C_L => self.t1_counter as Byte,
+            T1C_H => (self.t1_counter >> 8) as Byte,
+            T1L_L => self.t1_latch as Byte,
+            T1L_H => (self.t1_latch >> 8) as Byte,
+            T2C_L => self.t2_counter as Byte,
+            T2C_H => (self.t2_counter >> 8) as Byte,
+            SR => self.sr,
+            ACR => self.acr,
+            PCR => self.pcr,
+            IFR => self.flags(),
+            _ => self.ier | 0x80,
+        }
+    }
+
+    fn tick(&mut self) {
+        // the pulse output modes only hold ca2 and cb2 low for a cycle
+        if (self.pcr >> 1) & 0x07 == 0x05 {
+            self.ca2_out = true;
+        }
+        if (self.pcr >> 5) & 0x07 == 0x05 {
+            self.cb2_out = true;
+        }
+
+        self.tick_timer1();
+        self.tick_timer2();
+        if let 0x02 | 0x06 = self.shift_mode() {
+            self.shift();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.ifr & self.ier & 0x7F != 0
+    }
+}
+
+impl Default for Via {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,21 @@
+pub type Byte = u8;
+pub type Word = u16;
+
+pub mod bus;
+pub mod cpu;
+pub mod device;
+pub mod machine;
+pub mod mapper;
+pub mod memory_map;
+pub mod opcodes;
+
+pub const ADDRESS_LOW: u16 = 0x0000;
+pub const ADDRESS_HIGH: u16 = 0xFFFF;
+pub const MEMORY_RANGE: usize = (ADDRESS_HIGH - ADDRESS_LOW) as usize + 1;
+
+pub const STACK_LOW: u16 = 0x0100;
+pub const STACK_HIGH: u16 = 0x01FF;
+
+pub const NMI_VECTOR: u16 = 0xFFFA;
+pub const RESET_VECTOR: u16 = 0xFFFC;
+pub const IRQ_VECTOR: u16 = 0xFFFE;
@@ -0,0 +1,71 @@
+// ready made computers, a cpu and a memory map wired up the way a real board was
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::bus::Bus;
+use crate::cpu::{ExecError, CPU};
+use crate::memory_map::MemoryMap;
+
+pub mod apple1;
+pub mod ben_eater;
+pub mod kim1;
+
+pub struct Machine {
+    pub name: &'static str,
+    pub cpu: CPU,
+    pub bus: MemoryMap,
+    pub clock_hz: Option<u64>, // run in step with the real thing, or flat out when None
+}
+
+impl Machine {
+    // builds around a bus and starts from its reset vector
+    pub fn new(name: &'static str, cpu: CPU, bus: MemoryMap) -> Self {
+        let mut machine = Self {
+            name,
+            cpu,
+            bus,
+            clock_hz: None,
+        };
+        machine.reset();
+        machine
+    }
+
+    pub fn reset(&mut self) {
+        self.cpu.reset(&self.bus);
+    }
+
+    // runs until the cpu halts
+    pub fn run(&mut self) -> Result<(), ExecError> {
+        run(&mut self.cpu, &mut self.bus, self.clock_hz)
+    }
+
+    pub fn run_for_cycles(&mut self, cycles: u64) -> u64 {
+        self.cpu.run_for_cycles(&mut self.bus, cycles)
+    }
+}
+
+// runs until the cpu halts, sleeping now and then to keep to clock_hz
+pub fn run<B: Bus>(cpu: &mut CPU, bus: &mut B, clock_hz: Option<u64>) -> Result<(), ExecError> {
+    let start = Instant::now();
+    let first_cycle = cpu.cycles;
+    let mut next_sync = 0;
+    while !cpu.halted {
+        cpu.try_execute(bus)?;
+
+        let Some(clock_hz) = clock_hz else {
+            continue;
+        };
+        // a millisecond or so at a time is plenty
+        let cycles = cpu.cycles - first_cycle;
+        if cycles >= next_sync {
+            // whole seconds first, cycles in nanoseconds overflows a u64 in a few hours
+            let nanos = cycles % clock_hz * 1_000_000_000 / clock_hz;
+            let due = Duration::from_secs(cycles / clock_hz) + Duration::from_nanos(nanos);
+            if let Some(ahead) = due.checked_sub(start.elapsed()) {
+                thread::sleep(ahead);
+            }
+            next_sync = cycles + clock_hz / 1000;
+        }
+    }
+    Ok(())
+}
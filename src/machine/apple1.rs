@@ -0,0 +1,69 @@
+// the apple i, ram from $0000, a pia at $D010 for the keyboard and the display and the
+// woz monitor in the top page
+//
+// $E000-$EFFF is ram as well, it is where integer basic gets loaded
+use std::ops::RangeInclusive;
+
+use crate::cpu::CPU;
+use crate::device::pia::{Display, Keyboard, Pia};
+use crate::machine::Machine;
+use crate::memory_map::MemoryMap;
+use crate::{Byte, Word};
+
+pub const PIA: RangeInclusive<Word> = 0xD010..=0xD013;
+pub const WOZMON: Word = 0xFF00;
+pub const BASIC: Word = 0xE000;
+pub const CLOCK_HZ: u64 = 1_022_727;
+// the display shifts characters through at about sixty a second
+pub const DISPLAY_CYCLES: u32 = (CLOCK_HZ / 60) as u32;
+
+// ram_kb of ram at $0000, 4 to 32 in steps of 4, and the wozmon image, talking to the
+// terminal
+pub fn apple1(ram_kb: usize, wozmon: &[Byte]) -> Result<Machine, String> {
+    apple1_with(ram_kb, wozmon, Keyboard::stdin(), Display::stdout())
+}
+
+// the same machine with the keyboard and display hooked up elsewhere, the apple i
+// character set and display speed are applied on top of whatever they were set up with
+pub fn apple1_with(
+    ram_kb: usize,
+    wozmon: &[Byte],
+    mut keyboard: Keyboard,
+    mut display: Display,
+) -> Result<Machine, String> {
+    if !(4..=32).contains(&ram_kb) || ram_kb % 4 != 0 {
+        return Err(format!("{}K of ram, the apple i takes 4K to 32K", ram_kb));
+    }
+    if wozmon.len() != 0x100 {
+        return Err(format!(
+            "the woz monitor is 256 bytes, not {}",
+            wozmon.len()
+        ));
+    }
+
+    // seven bit ascii, uppercase only and return for enter, with the keyboard holding
+    // bit 7 high
+    keyboard.high_bit = true;
+    keyboard.uppercase = true;
+    keyboard.translate_newlines = true;
+    keyboard.backspace = Some(b'_');
+    display.uppercase = true;
+    display.printable_only = true;
+    display.busy_cycles = DISPLAY_CYCLES;
+
+    // the pia's interrupt lines aren't connected on the board
+    let mut pia = Pia::new();
+    pia.irq_wired = false;
+    pia.attach_keyboard(keyboard);
+    pia.attach_display(display);
+
+    let mut bus = MemoryMap::new();
+    bus.ram(0x0000..=(ram_kb * 1024 - 1) as Word);
+    bus.ram(BASIC..=0xEFFF);
+    bus.map_device(PIA, Box::new(pia));
+    bus.rom(WOZMON..=0xFFFF, wozmon);
+
+    let mut machine = Machine::new("apple i", CPU::new(), bus);
+    machine.clock_hz = Some(CLOCK_HZ);
+    Ok(machine)
+}
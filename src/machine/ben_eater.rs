@@ -0,0 +1,169 @@
+// the ben eater breadboard computer, 16K of ram, a 6522 via at $6000 driving a 16x2
+// lcd and 32K of rom at $8000
+//
+// the via is decoded over $6000-$7FFF so its registers repeat through that space
+use std::io::{stdout, Write};
+
+use crate::cpu::{Variant, CPU};
+use crate::device::hd44780::Hd44780;
+use crate::device::via::Via;
+use crate::device::Device;
+use crate::machine::Machine;
+use crate::memory_map::MemoryMap;
+use crate::{Byte, Word};
+
+pub const VIA: Word = 0x6000;
+pub const ROM: Word = 0x8000;
+pub const CLOCK_HZ: u64 = 1_000_000;
+
+// how the lcd hangs off the via
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LcdWiring {
+    #[default]
+    EightBit, // d0-d7 on port b, e, rw and rs on pa7, pa6 and pa5
+    FourBit, // d4-d7 on pb0-pb3, rs, rw and e on pb4, pb5 and pb6
+}
+
+// the via with the lcd on its ports, drawing the lcd to a writer when it changes
+pub struct LcdBoard {
+    pub via: Via,
+    pub lcd: Hd44780,
+    pub wiring: LcdWiring,
+    pub redraw_cycles: u64, // redraws no more often than this
+    output: Option<Box<dyn Write>>,
+    since_redraw: u64,
+    drawn: bool,
+}
+
+impl LcdBoard {
+    pub fn new(wiring: LcdWiring) -> Self {
+        Self {
+            via: Via::new(),
+            lcd: Hd44780::new(16),
+            wiring,
+            redraw_cycles: CLOCK_HZ / 30,
+            output: None,
+            since_redraw: 0,
+            drawn: false,
+        }
+    }
+
+    pub fn connect_output(&mut self, output: Box<dyn Write>) {
+        self.output = Some(output);
+    }
+
+    // the lcd in a box, with the cursor underlined
+    pub fn render(&self) -> String {
+        let lines = self.lcd.lines();
+        let border = format!("+{}+\n", "-".repeat(self.lcd.columns));
+        let mut screen = border.clone();
+        for (number, line) in lines.iter().enumerate() {
+            screen.push('|');
+            for (column, character) in line.chars().enumerate() {
+                let character = if self.lcd.display_on() {
+                    character
+                } else {
+                    ' '
+                };
+                match self.lcd.cursor() == Some((number, column)) {
+                    true => screen.push_str(&format!("\x1B[4m{}\x1B[0m", character)),
+                    false => screen.push(character),
+                }
+            }
+            screen.push_str("|\n");
+        }
+        screen.push_str(&border);
+        screen
+    }
+
+    // passes the via's pins to the lcd and anything the lcd drives back to the via
+    fn sync(&mut self) {
+        let (a, b) = (self.via.port_a(), self.via.port_b());
+        let (rs, rw, e, data) = match self.wiring {
+            LcdWiring::EightBit => (a & 0x20 != 0, a & 0x40 != 0, a & 0x80 != 0, b),
+            LcdWiring::FourBit => (b & 0x10 != 0, b & 0x20 != 0, b & 0x40 != 0, b << 4),
+        };
+        self.lcd.update(rs, rw, e, data);
+
+        let pins = match (self.wiring, self.lcd.output()) {
+            (_, None) => 0xFF,
+            (LcdWiring::EightBit, Some(value)) => value,
+            (LcdWiring::FourBit, Some(value)) => 0xF0 | value >> 4,
+        };
+        self.via.set_port_b(pins);
+    }
+
+    fn redraw(&mut self) {
+        let screen = self.render();
+        let height = screen.lines().count();
+        let Some(output) = self.output.as_mut() else {
+            return;
+        };
+        // draws over the last one
+        if self.drawn {
+            let _ = write!(output, "\x1B[{}A", height);
+        }
+        let _ = output.write_all(screen.as_bytes());
+        let _ = output.flush();
+        self.drawn = true;
+    }
+}
+
+impl Device for LcdBoard {
+    fn name(&self) -> &str {
+        "6522 via with hd44780 lcd"
+    }
+
+    fn read(&mut self, offset: Word) -> Byte {
+        self.sync();
+        let value = self.via.read(offset);
+        self.sync();
+        value
+    }
+
+    fn write(&mut self, offset: Word, value: Byte) {
+        self.via.write(offset, value);
+        self.sync();
+    }
+
+    fn peek(&self, offset: Word) -> Byte {
+        self.via.peek(offset)
+    }
+
+    fn tick(&mut self) {
+        self.via.tick();
+        self.lcd.tick();
+
+        self.since_redraw += 1;
+        if self.since_redraw >= self.redraw_cycles && self.lcd.take_changed() {
+            self.since_redraw = 0;
+            self.redraw();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.via.irq()
+    }
+}
+
+// a 65c02 with the rom image at $8000, drawing the lcd on the terminal
+pub fn ben_eater(rom: &[Byte], wiring: LcdWiring) -> Result<Machine, String> {
+    let mut board = LcdBoard::new(wiring);
+    board.connect_output(Box::new(stdout()));
+    ben_eater_with(rom, board)
+}
+
+pub fn ben_eater_with(rom: &[Byte], board: LcdBoard) -> Result<Machine, String> {
+    if rom.len() != 0x8000 {
+        return Err(format!("the rom is 32K, not {} bytes", rom.len()));
+    }
+
+    let mut bus = MemoryMap::new();
+    bus.ram(0x0000..=0x3FFF);
+    bus.map_device(VIA..=0x7FFF, Box::new(board));
+    bus.rom(ROM..=0xFFFF, rom);
+
+    let mut machine = Machine::new("ben eater 6502", CPU::with_variant(Variant::Cmos), bus);
+    machine.clock_hz = Some(CLOCK_HZ);
+    Ok(machine)
+}
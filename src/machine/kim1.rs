@@ -0,0 +1,588 @@
+// the kim-1, 1K of ram and two 6530s holding the monitor, with the keypad, the six
+// digit led display and the teletype hung off the 6530-002's ports
+//
+// only a0-a12 are decoded so the first 8K repeats all the way up, which is how the
+// vectors at the top of the 002's rom end up at $FFFA
+use std::io::{stdout, Write};
+use std::sync::mpsc::Receiver;
+
+use crate::bus::Bus;
+use crate::cpu::CPU;
+use crate::device::rriot::{self, Rriot};
+use crate::device::{Device, HostInput};
+use crate::machine::Machine;
+use crate::memory_map::MemoryMap;
+use crate::{Byte, Word};
+
+// the 6530-003, its rom has the cassette routines
+pub const IO_003: Word = 0x1700;
+pub const RAM_003: Word = 0x1780;
+pub const ROM_003: Word = 0x1800;
+// the 6530-002, its rom has the keypad, display and teletype routines
+pub const IO_002: Word = 0x1740;
+pub const RAM_002: Word = 0x17C0;
+pub const ROM_002: Word = 0x1C00;
+// the monitor jumps through these for nmi and irq
+pub const NMI_VECTOR: Word = 0x17FA;
+pub const IRQ_VECTOR: Word = 0x17FE;
+pub const MONITOR: Word = 0x1C00; // saves the registers and shows the pc
+pub const CLOCK_HZ: u64 = 1_000_000;
+
+// the monitor talks to whichever the jumper picks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Console {
+    #[default]
+    Keypad,
+    Tty, // the jumper pulls pa0 low while decoder output 3 is selected
+}
+
+// the keys as the monitor numbers them, 0-F then AD, DA, +, GO and PC, row by row
+// down the matrix with pa6 first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Matrix(Byte),
+    Stop, // ST, wired to nmi
+}
+
+impl Key {
+    // hex digits, @ for AD, = for DA, +, g for GO, p for PC and s for ST
+    fn from_host(byte: Byte) -> Option<Key> {
+        let key = match byte.to_ascii_lowercase() {
+            digit @ b'0'..=b'9' => Key::Matrix(digit - b'0'),
+            digit @ b'a'..=b'f' => Key::Matrix(digit - b'a' + 0x0A),
+            b'@' => Key::Matrix(0x10),
+            b'=' => Key::Matrix(0x11),
+            b'+' => Key::Matrix(0x12),
+            b'g' => Key::Matrix(0x13),
+            b'p' => Key::Matrix(0x14),
+            b's' => Key::Stop,
+            _ => return None,
+        };
+        Some(key)
+    }
+}
+
+// the keypad, a key pulls its pa line low while its row is selected on the decoder
+pub struct Keypad {
+    pub press_cycles: u64, // how long a key is held down, and then left up
+    held: Option<Key>,
+    countdown: u64,
+    input: HostInput,
+}
+
+impl Keypad {
+    // not connected to anything, feed it with press
+    pub fn new() -> Self {
+        Self {
+            press_cycles: CLOCK_HZ / 20,
+            held: None,
+            countdown: 0,
+            input: HostInput::new(),
+        }
+    }
+
+    pub fn stdin() -> Self {
+        Self {
+            input: HostInput::stdin(),
+            ..Self::new()
+        }
+    }
+
+    pub fn connect_input(&mut self, input: Receiver<Byte>) {
+        self.input.connect(input);
+    }
+
+    // host keys, anything without a key on the keypad is passed over
+    pub fn press(&mut self, keys: &[Byte]) {
+        self.input.push(keys);
+    }
+
+    // the pa lines for decoder output row
+    fn pins(&self, row: Byte) -> Byte {
+        match self.held {
+            Some(Key::Matrix(key)) if key / 7 == row => !(0x40 >> (key % 7)),
+            _ => 0xFF,
+        }
+    }
+
+    fn stop(&self) -> bool {
+        self.held == Some(Key::Stop)
+    }
+
+    fn tick(&mut self) {
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            // let go, then stay up as long again before the next key
+            if self.countdown == 0 && self.held.take().is_some() {
+                self.countdown = self.press_cycles;
+            }
+            return;
+        }
+        if let Some(key) = self.next_key() {
+            self.held = Some(key);
+            self.countdown = self.press_cycles;
+        }
+    }
+
+    fn next_key(&mut self) -> Option<Key> {
+        loop {
+            if let Some(key) = Key::from_host(self.input.poll()?) {
+                return Some(key);
+            }
+        }
+    }
+}
+
+impl Default for Keypad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the six digits, segments a-g on pa0-pa6 light while the decoder picks a digit on
+// outputs 4-9, what was lit over each frame is drawn when it changes
+pub struct Leds {
+    pub redraw_cycles: u64,
+    lit: [Byte; 6],
+    shown: [Byte; 6],
+    since_redraw: u64,
+    output: Option<Box<dyn Write>>,
+    drawn: bool,
+}
+
+impl Leds {
+    // the monitor's segment patterns for 0-F
+    const HEX: [Byte; 16] = [
+        0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F, 0x77, 0x7C, 0x39, 0x5E, 0x79,
+        0x71,
+    ];
+
+    // not connected to anything, read it with digits
+    pub fn new() -> Self {
+        Self {
+            redraw_cycles: CLOCK_HZ / 30,
+            lit: [0x00; 6],
+            shown: [0x00; 6],
+            since_redraw: 0,
+            output: None,
+            drawn: false,
+        }
+    }
+
+    pub fn stdout() -> Self {
+        let mut leds = Self::new();
+        leds.connect_output(Box::new(stdout()));
+        leds
+    }
+
+    pub fn connect_output(&mut self, output: Box<dyn Write>) {
+        self.output = Some(output);
+    }
+
+    // the last frame read back as hex, blank digits are spaces and anything else is ?
+    pub fn digits(&self) -> String {
+        self.shown
+            .iter()
+            .enumerate()
+            .map(|(digit, segments)| {
+                let gap = if digit == 4 { " " } else { "" };
+                let character = match Leds::HEX.iter().position(|hex| hex == segments) {
+                    Some(value) => char::from_digit(value as u32, 16).unwrap(),
+                    None if *segments == 0x00 => ' ',
+                    None => '?',
+                };
+                format!("{}{}", gap, character.to_ascii_uppercase())
+            })
+            .collect()
+    }
+
+    // the last frame drawn three lines high, the address apart from the data
+    pub fn render(&self) -> String {
+        let mut lines = [String::new(), String::new(), String::new()];
+        for (digit, segments) in self.shown.iter().enumerate() {
+            let on = |segment: u8, character: char| match segments & 1 << segment {
+                0 => ' ',
+                _ => character,
+            };
+            let gap = match digit {
+                0 => "",
+                4 => "   ",
+                _ => " ",
+            };
+            lines[0].push_str(&format!("{} {} ", gap, on(0, '_')));
+            lines[1].push_str(&format!(
+                "{}{}{}{}",
+                gap,
+                on(5, '|'),
+                on(6, '_'),
+                on(1, '|')
+            ));
+            lines[2].push_str(&format!(
+                "{}{}{}{}",
+                gap,
+                on(4, '|'),
+                on(3, '_'),
+                on(2, '|')
+            ));
+        }
+        lines.map(|line| line + "\n").concat()
+    }
+
+    fn light(&mut self, digit: usize, segments: Byte) {
+        self.lit[digit] |= segments & 0x7F;
+    }
+
+    fn tick(&mut self) {
+        self.since_redraw += 1;
+        if self.since_redraw < self.redraw_cycles {
+            return;
+        }
+        self.since_redraw = 0;
+        let lit = std::mem::take(&mut self.lit);
+        if lit != self.shown || !self.drawn {
+            self.shown = lit;
+            self.redraw();
+        }
+    }
+
+    fn redraw(&mut self) {
+        let screen = self.render();
+        let Some(output) = self.output.as_mut() else {
+            return;
+        };
+        // draws over the last one
+        if self.drawn {
+            let _ = write!(output, "\x1B[3A");
+        }
+        let _ = output.write_all(screen.as_bytes());
+        let _ = output.flush();
+        self.drawn = true;
+    }
+}
+
+impl Default for Leds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the teletype, the board clocks bits in on pa7 and out on pb0 with the line high
+// when idle, a start bit, eight data bits from bit 0 up and two stop bits
+pub struct Tty {
+    pub baud: u64,
+    pub clock_hz: u64,
+    pub uppercase: bool,
+    pub translate_newlines: bool,       // \n typed becomes \r
+    pub echo: bool,                     // the board loops what is typed back to the printer
+    sending: Option<(u16, u8, u64)>,    // the frame, bits left and cycles left of this bit
+    receiving: Option<(Byte, u8, u64)>, // the byte so far, bits had and cycles to the next
+    quiet: u64,                         // cycles since either end sent anything
+    input: HostInput,
+    output: Option<Box<dyn Write>>,
+    printed: Vec<Byte>,
+}
+
+impl Tty {
+    const FRAME_BITS: u8 = 11;
+
+    // not connected to anything, feed it with press and read it with take_printed
+    pub fn new() -> Self {
+        Self {
+            baud: 1200,
+            clock_hz: CLOCK_HZ,
+            uppercase: false,
+            translate_newlines: false,
+            echo: true,
+            sending: None,
+            receiving: None,
+            quiet: 0,
+            input: HostInput::new(),
+            output: None,
+            printed: Vec::new(),
+        }
+    }
+
+    // the terminal echoes what is typed itself
+    pub fn stdio() -> Self {
+        let mut tty = Self {
+            input: HostInput::stdin(),
+            translate_newlines: true,
+            echo: false,
+            ..Self::new()
+        };
+        tty.connect_output(Box::new(stdout()));
+        tty
+    }
+
+    pub fn connect_input(&mut self, input: Receiver<Byte>) {
+        self.input.connect(input);
+    }
+
+    pub fn connect_output(&mut self, output: Box<dyn Write>) {
+        self.output = Some(output);
+    }
+
+    pub fn press(&mut self, keys: &[Byte]) {
+        self.input.push(keys);
+    }
+
+    // characters printed since the last call when no output is connected
+    pub fn take_printed(&mut self) -> Vec<Byte> {
+        std::mem::take(&mut self.printed)
+    }
+
+    // what the board sees on pa7
+    pub fn line(&self) -> bool {
+        match self.sending {
+            Some((frame, _, _)) => frame & 0x01 != 0,
+            None => true,
+        }
+    }
+
+    fn bit_cycles(&self) -> u64 {
+        (self.clock_hz / self.baud).max(1)
+    }
+
+    // pb0 is the level the board drives towards the printer
+    fn tick(&mut self, pb0: bool) {
+        let bit_cycles = self.bit_cycles();
+        self.quiet += 1;
+        match self.sending.as_mut() {
+            Some((frame, bits, countdown)) => {
+                self.quiet = 0;
+                *countdown -= 1;
+                if *countdown == 0 {
+                    *frame >>= 1;
+                    *bits -= 1;
+                    *countdown = bit_cycles;
+                    if *bits == 0 {
+                        self.sending = None;
+                    }
+                }
+            }
+            // the monitor misses anything that arrives while it is busy printing, so
+            // nothing goes in until the line has been quiet for a couple of characters
+            None if self.quiet >= 2 * Tty::FRAME_BITS as u64 * bit_cycles => {
+                if let Some(key) = self.next_key() {
+                    let frame = (key as u16) << 1 | 0x600;
+                    self.sending = Some((frame, Tty::FRAME_BITS, bit_cycles));
+                }
+            }
+            None => {}
+        }
+
+        let line = pb0 && (self.line() || !self.echo);
+        self.listen(line, bit_cycles);
+    }
+
+    // samples the middle of each bit after a start bit
+    fn listen(&mut self, line: bool, bit_cycles: u64) {
+        let Some((byte, bits, countdown)) = self.receiving.as_mut() else {
+            if !line {
+                self.receiving = Some((0x00, 0, bit_cycles + bit_cycles / 2));
+            }
+            return;
+        };
+        self.quiet = 0;
+        *countdown -= 1;
+        if *countdown > 0 {
+            return;
+        }
+        match *bits {
+            // the middle of the first stop bit
+            8 => {
+                let byte = *byte;
+                self.receiving = None;
+                self.print(byte);
+            }
+            _ => {
+                *byte |= (line as Byte) << *bits;
+                *bits += 1;
+                *countdown = bit_cycles;
+            }
+        }
+    }
+
+    fn next_key(&mut self) -> Option<Byte> {
+        let mut key = self.input.poll()?;
+        if self.translate_newlines && key == b'\n' {
+            key = b'\r';
+        }
+        if self.uppercase {
+            key = key.to_ascii_uppercase();
+        }
+        Some(key)
+    }
+
+    fn print(&mut self, byte: Byte) {
+        let byte = byte & 0x7F;
+        // the monitor pads with nulls and rubouts
+        if byte == 0x00 || byte == 0x7F {
+            return;
+        }
+        match self.output.as_mut() {
+            Some(output) => {
+                let _ = output.write_all(&[byte]);
+                let _ = output.flush();
+            }
+            None => self.printed.push(byte),
+        }
+    }
+}
+
+impl Default for Tty {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the 6530-002 with the keypad, the leds and the teletype on its ports, pb1-pb4 go
+// through a 74145 that picks a keypad row or a digit
+pub struct KimBoard {
+    pub rriot: Rriot,
+    pub console: Console,
+    pub keypad: Keypad,
+    pub leds: Leds,
+    pub tty: Tty,
+}
+
+impl KimBoard {
+    pub fn new(rom: &[Byte], console: Console) -> Self {
+        Self {
+            rriot: Rriot::new(rom),
+            console,
+            keypad: Keypad::new(),
+            leds: Leds::new(),
+            tty: Tty::new(),
+        }
+    }
+
+    fn decoder(&self) -> Byte {
+        (self.rriot.port_b() >> 1) & 0x0F
+    }
+
+    // puts the keypad or the teletype and its jumper on the pa pins
+    fn sync(&mut self) {
+        let decoder = self.decoder();
+        let pins = match self.console {
+            Console::Keypad => self.keypad.pins(decoder),
+            Console::Tty => {
+                let line = match self.tty.line() {
+                    true => 0xFF,
+                    false => 0x7F,
+                };
+                match decoder {
+                    3 => line & 0xFE,
+                    _ => line,
+                }
+            }
+        };
+        self.rriot.set_port_a(pins);
+    }
+}
+
+impl Device for KimBoard {
+    fn name(&self) -> &str {
+        "6530-002 with the kim-1 keypad, display and teletype"
+    }
+
+    fn read(&mut self, offset: Word) -> Byte {
+        self.rriot.read(offset)
+    }
+
+    fn write(&mut self, offset: Word, value: Byte) {
+        self.rriot.write(offset, value);
+        self.sync();
+    }
+
+    fn peek(&self, offset: Word) -> Byte {
+        self.rriot.peek(offset)
+    }
+
+    fn tick(&mut self) {
+        self.rriot.tick();
+        match self.console {
+            Console::Keypad => {
+                self.keypad.tick();
+                if let digit @ 0..=5 = self.decoder().wrapping_sub(4) {
+                    let segments = self.rriot.port_a() & self.rriot.port_a_ddr();
+                    self.leds.light(digit as usize, segments);
+                }
+                self.leds.tick();
+            }
+            Console::Tty => {
+                let pb0 = self.rriot.port_b() & 0x01 != 0;
+                self.tty.tick(pb0);
+            }
+        }
+        self.sync();
+    }
+
+    fn irq(&self) -> bool {
+        self.rriot.irq()
+    }
+
+    fn nmi(&self) -> bool {
+        self.console == Console::Keypad && self.keypad.stop()
+    }
+}
+
+// the 2K monitor image from $1800, the 003's rom then the 002's, with the console on
+// the terminal
+pub fn kim1(monitor: &[Byte], console: Console) -> Result<Machine, String> {
+    kim1_with(
+        monitor,
+        console,
+        Keypad::stdin(),
+        Leds::stdout(),
+        Tty::stdio(),
+    )
+}
+
+// the same machine with the keypad, leds and teletype hooked up elsewhere, only the
+// one the console picks is used
+pub fn kim1_with(
+    monitor: &[Byte],
+    console: Console,
+    keypad: Keypad,
+    leds: Leds,
+    mut tty: Tty,
+) -> Result<Machine, String> {
+    if monitor.len() != 0x800 {
+        return Err(format!(
+            "the kim monitor is 2K, not {} bytes",
+            monitor.len()
+        ));
+    }
+
+    // after a reset the monitor times a rubout to find the baud rate
+    tty.clock_hz = CLOCK_HZ;
+    if console == Console::Tty {
+        tty.input.push_front(0x7F);
+    }
+
+    let mut board = KimBoard::new(&monitor[0x400..], console);
+    board.keypad = keypad;
+    board.leds = leds;
+    board.tty = tty;
+
+    let mut bus = MemoryMap::new();
+    bus.ram(0x0000..=0x03FF);
+    let rriot_003 = bus.map_device(ROM_003..=0x1BFF, Box::new(Rriot::new(&monitor[..0x400])));
+    bus.map_device_window(IO_003..=0x173F, rriot_003, rriot::IO);
+    bus.map_device_window(RAM_003..=0x17BF, rriot_003, rriot::RAM);
+    let rriot_002 = bus.map_device(ROM_002..=0x1FFF, Box::new(board));
+    bus.map_device_window(IO_002..=0x177F, rriot_002, rriot::IO);
+    bus.map_device_window(RAM_002..=0x17FF, rriot_002, rriot::RAM);
+    bus.mirror(0x2000..=0xFFFF, 0x0000..=0x1FFF);
+
+    // ST and BRK land back in the monitor, the first thing anyone keys in
+    for vector in [NMI_VECTOR, IRQ_VECTOR] {
+        bus.write(vector, MONITOR as Byte);
+        bus.write(vector + 1, (MONITOR >> 8) as Byte);
+    }
+
+    let mut machine = Machine::new("kim-1", CPU::new(), bus);
+    machine.clock_hz = Some(CLOCK_HZ);
+    Ok(machine)
+}
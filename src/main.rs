@@ -1,31 +1,24 @@
 use std::{
-    alloc::System,
-    io::{stdin, stdout, BufRead, Write},
-    ops::Add,
+    fs,
+    io::{stdout, Write},
     process::exit,
 };
 
-use crate::cpu::{load_memory, make_address, save_memory, split_address, xextend, CPU, MEMORY};
+use emu6502::bus::Bus;
+use emu6502::cpu::CPU;
+use emu6502::device::acia::Acia;
+use emu6502::device::read_stdin_line;
+use emu6502::machine::ben_eater::{self, LcdWiring};
+use emu6502::machine::kim1::{self, Console};
+use emu6502::machine::{self, apple1};
+use emu6502::mapper::from_ines;
+use emu6502::memory_map::MemoryMap;
+use emu6502::opcodes::disassemble;
 
 use logos::Logos;
-use std::fs;
-use std::fs::File;
-use std::io::Read;
-
-type Byte = u8;
-type Word = u16;
-
-mod cpu;
-
-
-const ADDRESS_LOW: u16 = 0x0000;
-const ADDRESS_HIGH: u16 = 0xFFFF;
-const MEMORY_RANGE: usize = (ADDRESS_HIGH - ADDRESS_LOW) as usize + 1;
-
-const STACK_LOW: u16 = 0x0100;
-const STACK_HIGH: u16 = 0x01FF;
 
 #[derive(Logos, Debug, PartialEq, Clone)]
+#[allow(clippy::upper_case_acronyms)]
 enum InterpreterInstr {
     // utility
     #[token("reg")]
@@ -78,6 +71,24 @@ enum InterpreterInstr {
     LoadAccumulator,
     #[token("execute")]
     Execute,
+    #[token("disasm")]
+    Disassemble,
+    #[token("banks")]
+    Banks,
+    #[token("ines")]
+    Ines,
+    #[token("devices")]
+    Devices,
+    #[token("acia")]
+    Acia,
+    #[token("run")]
+    Run,
+    #[token("apple1")]
+    Apple1,
+    #[token("breadboard")]
+    Breadboard,
+    #[token("kim1")]
+    Kim1,
     #[error]
     #[regex(r"[\t\n\f ]+", logos::skip)]
     ERROR,
@@ -86,14 +97,19 @@ enum InterpreterInstr {
 fn main() {
     // test
     let mut _cpu = CPU::default();
-    let mut _mem = MEMORY::default();
+    let mut _mem = MemoryMap::new();
+    _mem.ram(0x0000..=0xFFFF);
 
-    // REPL
-    for line in stdin().lock().lines() {
+    // REPL, lines come through the same reader as the devices once one has taken stdin
+    loop {
         print!("> ");
-        stdout().flush();
+        let _ = stdout().flush();
 
-        let expression = line.unwrap();
+        let mut expression = String::new();
+        match read_stdin_line(&mut expression) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
         let lexer = InterpreterInstr::lexer(&expression);
         let instructions: Vec<_> = lexer
             .spanned()
@@ -103,7 +119,7 @@ fn main() {
         for instr in instructions.iter() {
             match instr.0 {
                 InterpreterInstr::Registers => {
-                    print!("\x1B[2J\n");
+                    println!("\x1B[2J");
                     println!("acc: {:?}", _cpu.acc);
                     println!("x: {:?}", _cpu.x);
                     println!("y: {:?}", _cpu.y);
@@ -130,8 +146,8 @@ fn main() {
 
                 InterpreterInstr::Stkptr => {
                     let value = expression.split_ascii_whitespace().nth(1).unwrap();
-                    let word = u16::from_str_radix(value, 16).unwrap();
-                    _cpu.stkptr = word;
+                    let byte = u8::from_str_radix(value, 16).unwrap();
+                    _cpu.stkptr = byte;
                 }
 
                 InterpreterInstr::Prgmctr => {
@@ -141,8 +157,8 @@ fn main() {
                 }
 
                 InterpreterInstr::Reset => {
-                    _cpu.reset();
-                    _mem.reset();
+                    _mem.memory.reset();
+                    _cpu.reset(&_mem);
                 }
                 InterpreterInstr::Status => {
                     print!("\x1B[2J");
@@ -162,7 +178,7 @@ fn main() {
                     let address = expression.split_ascii_whitespace().nth(1).unwrap();
                     let hex = u16::from_str_radix(address, 16).unwrap();
 
-                    println!("{}", _mem.get_byte(hex));
+                    println!("{}", _mem.peek(hex));
                 }
 
                 InterpreterInstr::SetByte => {
@@ -172,7 +188,7 @@ fn main() {
                     let value = expression.split_ascii_whitespace().nth(2).unwrap();
                     let byte = u8::from_str_radix(value, 16).unwrap();
 
-                    _mem.set_byte(hex, byte);
+                    _mem.write(hex, byte);
                 }
 
                 InterpreterInstr::Jump => {
@@ -182,7 +198,9 @@ fn main() {
                     _cpu.jmp(hex);
                 }
                 InterpreterInstr::Execute => {
-                    _cpu.execute(&mut _mem);
+                    if let Err(error) = _cpu.try_execute(&mut _mem) {
+                        println!("{}", error);
+                    }
                 }
                 InterpreterInstr::LoadAccumulator => {
                     let value = expression.split_ascii_whitespace().nth(1).unwrap();
@@ -200,24 +218,199 @@ fn main() {
                     let byte = u8::from_str_radix(value, 16).unwrap();
                     _cpu.push(&mut _mem, byte)
                 }
+                InterpreterInstr::Disassemble => {
+                    let mut address = match expression.split_ascii_whitespace().nth(1) {
+                        Some(value) => u16::from_str_radix(value, 16).unwrap(),
+                        None => _cpu.prgmctr,
+                    };
+                    for _ in 0..8 {
+                        let (text, bytes) = disassemble(_cpu.variant, &_mem, address);
+                        println!("{:04X}  {}", address, text);
+                        address = address.wrapping_add(bytes as u16);
+                    }
+                }
+                InterpreterInstr::Banks => {
+                    for mapper in _mem.mappers() {
+                        println!("{}", mapper.name());
+                        for (window, bank) in mapper.banks() {
+                            println!("  {}: {}", window, bank);
+                        }
+                    }
+                }
+                InterpreterInstr::Devices => {
+                    for (index, device) in _mem.devices().iter().enumerate() {
+                        println!("{}: {}", index, device.name());
+                    }
+                }
+                InterpreterInstr::Acia => {
+                    let value = expression.split_ascii_whitespace().nth(1).unwrap();
+                    let address = u16::from_str_radix(value, 16).unwrap();
+                    _mem.map_device(address..=address.saturating_add(3), Box::new(Acia::stdio()));
+                }
+                InterpreterInstr::Run => {
+                    // the terminal belongs to the machine until it halts
+                    while !_cpu.halted {
+                        if let Err(error) = _cpu.try_execute(&mut _mem) {
+                            println!("{}", error);
+                            break;
+                        }
+                    }
+                }
+                // apple1 <wozmon image> [ram in K] [integer basic image]
+                InterpreterInstr::Apple1 => {
+                    let mut args = expression.split_ascii_whitespace().skip(1);
+                    let wozmon = args.next().unwrap();
+                    let ram_kb = args.next().map_or(8, |kb| kb.parse().unwrap());
+                    let basic = args.next();
+                    let built = fs::read(wozmon)
+                        .map_err(|error| error.to_string())
+                        .and_then(|image| apple1::apple1(ram_kb, &image));
+                    let mut apple = match built {
+                        Ok(apple) => apple,
+                        Err(error) => {
+                            println!("{}", error);
+                            break;
+                        }
+                    };
+                    if let Some(basic) = basic {
+                        match fs::read(basic) {
+                            Ok(image) => {
+                                for (offset, byte) in image.iter().take(0x1000).enumerate() {
+                                    apple.bus.write(apple1::BASIC + offset as u16, *byte);
+                                }
+                            }
+                            Err(error) => println!("{}", error),
+                        }
+                    }
+                    _cpu = apple.cpu;
+                    _mem = apple.bus;
+                    if let Err(error) = machine::run(&mut _cpu, &mut _mem, apple.clock_hz) {
+                        println!("{}", error);
+                    }
+                    break;
+                }
+                // breadboard <32K rom image> [4bit]
+                InterpreterInstr::Breadboard => {
+                    let mut args = expression.split_ascii_whitespace().skip(1);
+                    let rom = args.next().unwrap();
+                    let wiring = match args.next() {
+                        Some("4bit") => LcdWiring::FourBit,
+                        _ => LcdWiring::EightBit,
+                    };
+                    let built = fs::read(rom)
+                        .map_err(|error| error.to_string())
+                        .and_then(|image| ben_eater::ben_eater(&image, wiring));
+                    match built {
+                        Ok(mut breadboard) => {
+                            if let Err(error) = breadboard.run() {
+                                println!("{}", error);
+                            }
+                            _cpu = breadboard.cpu;
+                            _mem = breadboard.bus;
+                        }
+                        Err(error) => println!("{}", error),
+                    }
+                    break;
+                }
+                // kim1 <2K monitor image> [tty] [program image] [hex load address]
+                InterpreterInstr::Kim1 => {
+                    let mut args = expression.split_ascii_whitespace().skip(1).peekable();
+                    let monitor = args.next().unwrap();
+                    let console = match args.next_if_eq(&"tty") {
+                        Some(_) => Console::Tty,
+                        None => Console::Keypad,
+                    };
+                    let program = args.next();
+                    let start = args
+                        .next()
+                        .map_or(0x0200, |start| u16::from_str_radix(start, 16).unwrap());
+                    let built = fs::read(monitor)
+                        .map_err(|error| error.to_string())
+                        .and_then(|image| kim1::kim1(&image, console));
+                    let mut kim = match built {
+                        Ok(kim) => kim,
+                        Err(error) => {
+                            println!("{}", error);
+                            break;
+                        }
+                    };
+                    if let Some(program) = program {
+                        match fs::read(program) {
+                            Ok(image) => {
+                                for (offset, byte) in image.iter().enumerate() {
+                                    kim.bus.write(start.wrapping_add(offset as u16), *byte);
+                                }
+                            }
+                            Err(error) => println!("{}", error),
+                        }
+                    }
+                    if let Err(error) = kim.run() {
+                        println!("{}", error);
+                    }
+                    _cpu = kim.cpu;
+                    _mem = kim.bus;
+                    break;
+                }
+                InterpreterInstr::Ines => {
+                    let path = expression.split_ascii_whitespace().nth(1).unwrap();
+                    match fs::read(path)
+                        .map_err(|error| error.to_string())
+                        .and_then(|image| from_ines(&image))
+                    {
+                        Ok(mapper) => {
+                            // the cartridge's work ram sits under the rom
+                            _mem.ram(0x6000..=0x7FFF);
+                            _mem.map_banked(0x8000..=0xFFFF, mapper);
+                        }
+                        Err(error) => println!("{}", error),
+                    }
+                    // the rest of the line is a path, not more commands
+                    break;
+                }
                 InterpreterInstr::Dump => {
-                    save_memory(&_mem, "memory.dump");
+                    if let Err(error) = _mem.save_snapshot("memory.dump") {
+                        println!("{}", error);
+                    }
                 }
                 InterpreterInstr::Load => {
-                    load_memory(&mut _mem, "memory.dump");
+                    if let Err(error) = _mem.load_snapshot("memory.dump") {
+                        println!("{}", error);
+                    }
                 }
                 _ => {}
             }
         }
-
     }
 }
 
 #[cfg(test)]
+#[allow(non_snake_case, clippy::bool_assert_comparison)]
 mod tests {
+    use std::fs;
     use std::path::Path;
 
     use super::*;
+    use emu6502::cpu::{load_memory, save_memory, MEMORY};
+    use emu6502::cpu::{
+        make_address, split_address, xextend, DecimalMode, ExecError, IllegalOpcodePolicy,
+        StackEvent, Status, Variant,
+    };
+    use emu6502::device::acia::{self, Acia};
+    use emu6502::device::hd44780::Hd44780;
+    use emu6502::device::pia::{self, Display, Keyboard, Pia};
+    use emu6502::device::riot::{self, Riot};
+    use emu6502::device::rriot::{self, Rriot};
+    use emu6502::device::via::{self, Via};
+    use emu6502::device::{spawn_reader, Device};
+    use emu6502::machine::apple1::{self, apple1_with};
+    use emu6502::machine::ben_eater::{ben_eater_with, LcdBoard, LcdWiring};
+    use emu6502::machine::kim1::{kim1_with, Keypad, KimBoard, Leds, Tty};
+    use emu6502::mapper::{Cnrom, Latch, Mmc1, Nrom, Uxrom};
+    use emu6502::memory_map::{MemoryMap, Region, RomWrites};
+    use emu6502::opcodes::{
+        disassemble, opcode, Mnemonic, Mode, Opcode, CMOS_OPCODES, NMOS_OPCODES,
+    };
+    use emu6502::Byte;
 
     #[test]
     fn test_cpu_jmp() {
@@ -225,8 +418,8 @@ mod tests {
         let mut mem = MEMORY::new();
 
         mem.set_byte(0x0000, 0x4C);
-        mem.set_byte(0x0001, 0xAA);
-        mem.set_byte(0x0002, 0x55);
+        mem.set_byte(0x0001, 0x55);
+        mem.set_byte(0x0002, 0xAA);
 
         cpu.execute(&mut mem);
 
@@ -236,27 +429,40 @@ mod tests {
     #[test]
     fn test_cpu_register_reset() {
         let mut cpu = CPU::new();
+        let memory = MEMORY::new();
         cpu.x = 0x50;
-        cpu.reset();
+        cpu.reset(&memory);
         assert_eq!(cpu.x, 0x00);
     }
     #[test]
     fn test_cpu_status_reset() {
         let mut cpu = CPU::new();
+        let memory = MEMORY::new();
         cpu.status.v = true;
-        cpu.reset();
+        cpu.reset(&memory);
         assert_eq!(cpu.status.v, false);
+        assert!(cpu.status.i);
     }
     #[test]
     fn test_cpu_complete_reset() {
         let mut cpu = CPU::new();
+        let memory = MEMORY::new();
         cpu.x = 0x50;
-        cpu.reset();
+        cpu.reset(&memory);
         assert_eq!(cpu.x, 0x00);
         cpu.status.v = true;
-        cpu.reset();
+        cpu.reset(&memory);
         assert_eq!(cpu.status.v, false);
     }
+    #[test]
+    fn test_cpu_reset_vector() {
+        let mut cpu = CPU::new();
+        let mut memory = MEMORY::new();
+        memory.set_byte(0xFFFC, 0x00);
+        memory.set_byte(0xFFFD, 0xE0);
+        cpu.reset(&memory);
+        assert_eq!(cpu.prgmctr, 0xE000);
+    }
 
     #[test]
     fn test_memory_reset() {
@@ -367,46 +573,42 @@ mod tests {
         assert_eq!(cpu.acc, 0x33);
     }
 
-    
-
     #[test]
     fn test_cpu_lda_zeropage_indirectX() {
-        // first add operand1 into accumulator.
-        // zp byte at operand1
-        // zp byte at operand2
-        // make address of zp bytes
+        // add x to the zero page operand
+        // read the low byte of the address at that zero page location
+        // read the high byte from the following zero page location
         // load byte at address into accumulator
 
         let mut memory = MEMORY::new();
         let mut cpu = CPU::new();
+        cpu.x = 0x04;
         memory.set_byte(0x0000, 0xA1); // lda (zp, x)
-        memory.set_byte(0x0001, 0x42); // operand1
-        memory.set_byte(0x0042, 0x81); // operand1 byte
-        memory.set_byte(0x0002, 0x16); // operand2
-        memory.set_byte(0x0016, 0x08); // operand2 byte
+        memory.set_byte(0x0001, 0x42); // operand
+        memory.set_byte(0x0046, 0x08); // low byte
+        memory.set_byte(0x0047, 0x81); // high byte
         memory.set_byte(0x8108, 0x55);
         cpu.execute(&mut memory);
         assert_eq!(cpu.acc, 0x55);
-        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x0002);
     }
-    
+
     #[test]
     fn test_cpu_lda_zeropageY() {
         let mut memory = MEMORY::new();
         let mut cpu = CPU::new();
+        cpu.y = 0x10;
 
-        memory.set_byte(0x0000, 0xB1); // lda (zp, x)
-        memory.set_byte(0x0001, 0x42); // operand1
-        memory.set_byte(0x0042, 0x81); // operand1 byte
-        memory.set_byte(0x0002, 0x16); // operand2
-        memory.set_byte(0x0016, 0x08); // operand2 byte
-        memory.set_byte(0x8108, 0x55);
+        memory.set_byte(0x0000, 0xB1); // lda (zp), y
+        memory.set_byte(0x0001, 0x42); // operand
+        memory.set_byte(0x0042, 0x08); // low byte
+        memory.set_byte(0x0043, 0x81); // high byte
+        memory.set_byte(0x8118, 0x55);
 
         cpu.execute(&mut memory);
         assert_eq!(cpu.acc, 0x55);
-        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x0002);
     }
-    
 
     #[test]
     fn test_fn_make_address() {
@@ -512,27 +714,60 @@ mod tests {
         cpu.status.c = true;
 
         let status: Byte = cpu.status.to_byte();
-        assert_eq!(status, 0x81);
+        assert_eq!(status, 0xA1);
+    }
+    #[test]
+    fn test_status_from_byte() {
+        let status = Status::from_byte(0b1101_1011);
+        assert!(status.n);
+        assert!(status.v);
+        assert!(!status.b);
+        assert!(status.d);
+        assert!(!status.i);
+        assert!(status.z);
+        assert!(status.c);
+        assert_eq!(status.to_byte(), 0b1110_1011);
     }
     #[test]
     fn test_cpu_php() {
         let mut memory = MEMORY::new();
         let mut cpu = CPU::new();
+        cpu.status.n = true;
+        cpu.status.c = true;
         memory.set_byte(0x0000, 0x08);
-        memory.set_byte(0x0001, 0x81);
         cpu.execute(&mut memory);
-        assert_eq!(cpu.status.n, true);
-        assert_eq!(cpu.status.c, true);
+        assert_eq!(memory.get_byte(0x01FF), 0xB1);
+        assert_eq!(cpu.stkptr, 0xFE);
+        assert_eq!(cpu.prgmctr, 0x0001);
     }
     #[test]
     fn test_cpu_plp() {
         let mut memory = MEMORY::new();
         let mut cpu = CPU::new();
-        cpu.status.n = true;
-        cpu.status.c = true;
+        cpu.push(&mut memory, 0x81);
         memory.set_byte(0x0000, 0x28);
         cpu.execute(&mut memory);
-        assert_eq!(cpu.status.to_byte(), 0x81);
+        assert!(cpu.status.n);
+        assert!(cpu.status.c);
+        assert_eq!(cpu.status.to_byte(), 0xA1);
+        assert_eq!(cpu.stkptr, 0xFF);
+    }
+    #[test]
+    fn test_cpu_php_plp_round_trip() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.status.v = true;
+        cpu.status.d = true;
+        cpu.status.z = true;
+        memory.set_byte(0x0000, 0x08);
+        memory.set_byte(0x0001, 0x28);
+        cpu.execute(&mut memory);
+        cpu.status = Status::default();
+        cpu.execute(&mut memory);
+        assert!(cpu.status.v);
+        assert!(cpu.status.d);
+        assert!(cpu.status.z);
+        assert!(!cpu.status.b);
     }
 
     // linux IO may cause this unit test to fail
@@ -663,14 +898,23 @@ mod tests {
         assert_eq!(cpu.y, 0x54);
     }
 
-    // test the cpu so it resets the stack pointer
+    // brk pushes the address past its padding byte and the status with b set
     #[test]
     fn test_cpu_brk() {
         let mut memory = MEMORY::new();
         let mut cpu = CPU::new();
-        memory.set_byte(0x0000, 0x00);
+        cpu.prgmctr = 0x0300;
+        cpu.status.c = true;
+        memory.set_byte(0x0300, 0x00);
+        memory.set_byte(0xFFFE, 0x00);
+        memory.set_byte(0xFFFF, 0x90);
         cpu.execute(&mut memory);
-        assert_eq!(cpu.prgmctr, 0x01);
+        assert_eq!(cpu.prgmctr, 0x9000);
+        assert_eq!(memory.get_byte(0x01FF), 0x03);
+        assert_eq!(memory.get_byte(0x01FE), 0x02);
+        assert_eq!(memory.get_byte(0x01FD), 0x31);
+        assert!(cpu.status.i);
+        assert!(!cpu.status.b);
     }
     #[test]
     fn test_cpu_stx() {
@@ -678,10 +922,13 @@ mod tests {
         let mut cpu = CPU::new();
 
         memory.set_byte(0x0000, 0x86);
+        memory.set_byte(0x0001, 0x40);
         cpu.x = 0xAA;
         cpu.execute(&mut memory);
 
-        assert_eq!(memory.get_byte(0x0000), 0xAA);
+        assert_eq!(memory.get_byte(0x0040), 0xAA);
+        assert_eq!(memory.get_byte(0x0000), 0x86);
+        assert_eq!(cpu.prgmctr, 0x0002);
     }
 
     #[test]
@@ -690,10 +937,13 @@ mod tests {
         let mut cpu = CPU::new();
 
         memory.set_byte(0x0000, 0x84);
+        memory.set_byte(0x0001, 0x40);
         cpu.y = 0xFF;
         cpu.execute(&mut memory);
 
-        assert_eq!(memory.get_byte(0x0000), 0xFF);
+        assert_eq!(memory.get_byte(0x0040), 0xFF);
+        assert_eq!(memory.get_byte(0x0000), 0x84);
+        assert_eq!(cpu.prgmctr, 0x0002);
     }
 
     #[test]
@@ -701,8 +951,2686 @@ mod tests {
         let mut memory = MEMORY::new();
         let mut cpu = CPU::new();
         memory.set_byte(0x0000, 0x85);
+        memory.set_byte(0x0001, 0x40);
         cpu.acc = 0x14;
         cpu.execute(&mut memory);
-        assert_eq!(memory.get_byte(0x0000), 0x14);
+        assert_eq!(memory.get_byte(0x0040), 0x14);
+        assert_eq!(memory.get_byte(0x0000), 0x85);
+        assert_eq!(cpu.prgmctr, 0x0002);
+    }
+
+    #[test]
+    fn test_cpu_adc_immediate() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.acc = 0x10;
+        cpu.status.c = true;
+        memory.set_byte(0x0000, 0x69);
+        memory.set_byte(0x0001, 0x22);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x33);
+        assert!(!cpu.status.c);
+        assert_eq!(cpu.prgmctr, 0x0002);
+    }
+    #[test]
+    fn test_cpu_adc_carry_and_overflow() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.acc = 0x80;
+        memory.set_byte(0x0000, 0x69);
+        memory.set_byte(0x0001, 0x80);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x00);
+        assert!(cpu.status.c);
+        assert!(cpu.status.v);
+        assert!(cpu.status.z);
+    }
+    #[test]
+    fn test_cpu_sbc_absolute() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.acc = 0x50;
+        cpu.status.c = true;
+        memory.set_byte(0x0000, 0xED);
+        memory.set_byte(0x0001, 0x34);
+        memory.set_byte(0x0002, 0x12);
+        memory.set_byte(0x1234, 0x60);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0xF0);
+        assert!(!cpu.status.c);
+        assert!(cpu.status.n);
+        assert_eq!(cpu.prgmctr, 0x0003);
+    }
+    #[test]
+    fn test_cpu_logical() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.acc = 0b1100_1100;
+        memory.set_byte(0x0000, 0x29); // and #
+        memory.set_byte(0x0001, 0b1010_1010);
+        memory.set_byte(0x0002, 0x09); // ora #
+        memory.set_byte(0x0003, 0b0000_0001);
+        memory.set_byte(0x0004, 0x49); // eor #
+        memory.set_byte(0x0005, 0b1000_1001);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0b1000_1000);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0b1000_1001);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x00);
+        assert!(cpu.status.z);
+    }
+    #[test]
+    fn test_cpu_cmp() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.acc = 0x40;
+        memory.set_byte(0x0000, 0xC9);
+        memory.set_byte(0x0001, 0x40);
+        cpu.execute(&mut memory);
+        assert!(cpu.status.z);
+        assert!(cpu.status.c);
+    }
+    #[test]
+    fn test_cpu_cpx_cpy() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.x = 0x10;
+        cpu.y = 0x30;
+        memory.set_byte(0x0000, 0xE0);
+        memory.set_byte(0x0001, 0x20);
+        memory.set_byte(0x0002, 0xC0);
+        memory.set_byte(0x0003, 0x20);
+        cpu.execute(&mut memory);
+        assert!(!cpu.status.c);
+        assert!(cpu.status.n);
+        cpu.execute(&mut memory);
+        assert!(cpu.status.c);
+        assert!(!cpu.status.z);
+    }
+    #[test]
+    fn test_cpu_bit() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.acc = 0x01;
+        memory.set_byte(0x0000, 0x24);
+        memory.set_byte(0x0001, 0x10);
+        memory.set_byte(0x0010, 0xC0);
+        cpu.execute(&mut memory);
+        assert!(cpu.status.z);
+        assert!(cpu.status.n);
+        assert!(cpu.status.v);
+    }
+    #[test]
+    fn test_cpu_asl_accumulator() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.acc = 0x81;
+        memory.set_byte(0x0000, 0x0A);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x02);
+        assert!(cpu.status.c);
+        assert_eq!(cpu.prgmctr, 0x0001);
+    }
+    #[test]
+    fn test_cpu_lsr_zeropage() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        memory.set_byte(0x0000, 0x46);
+        memory.set_byte(0x0001, 0x20);
+        memory.set_byte(0x0020, 0x03);
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0020), 0x01);
+        assert!(cpu.status.c);
+    }
+    #[test]
+    fn test_cpu_rol_ror() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.acc = 0x80;
+        cpu.status.c = true;
+        memory.set_byte(0x0000, 0x2A);
+        memory.set_byte(0x0001, 0x6A);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x01);
+        assert!(cpu.status.c);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x80);
+        assert!(cpu.status.c);
+    }
+    #[test]
+    fn test_cpu_inc_dec() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        memory.set_byte(0x0000, 0xEE);
+        memory.set_byte(0x0001, 0x00);
+        memory.set_byte(0x0002, 0x30);
+        memory.set_byte(0x0003, 0xC6);
+        memory.set_byte(0x0004, 0x10);
+        memory.set_byte(0x3000, 0xFF);
+        memory.set_byte(0x0010, 0x00);
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x3000), 0x00);
+        assert!(cpu.status.z);
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0010), 0xFF);
+        assert!(cpu.status.n);
+    }
+    #[test]
+    fn test_cpu_branch() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.prgmctr = 0x0200;
+        memory.set_byte(0x0200, 0xD0); // bne taken forward
+        memory.set_byte(0x0201, 0x10);
+        memory.set_byte(0x0212, 0xF0); // beq not taken
+        memory.set_byte(0x0213, 0x10);
+        memory.set_byte(0x0214, 0x90); // bcc taken backward
+        memory.set_byte(0x0215, 0xEA);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x0212);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x0214);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x0200);
+    }
+    #[test]
+    fn test_cpu_jmp_indirect() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        memory.set_byte(0x0000, 0x6C);
+        memory.set_byte(0x0001, 0xFF);
+        memory.set_byte(0x0002, 0x10);
+        memory.set_byte(0x10FF, 0x34);
+        memory.set_byte(0x1000, 0x12); // the high byte does not cross the page
+        memory.set_byte(0x1100, 0x56);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x1234);
+    }
+    #[test]
+    fn test_cpu_jsr_rts() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.prgmctr = 0x0300;
+        memory.set_byte(0x0300, 0x20);
+        memory.set_byte(0x0301, 0x00);
+        memory.set_byte(0x0302, 0x40);
+        memory.set_byte(0x4000, 0x60);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x4000);
+        assert_eq!(memory.get_byte(0x01FF), 0x03);
+        assert_eq!(memory.get_byte(0x01FE), 0x02);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x0303);
+    }
+    #[test]
+    fn test_cpu_rti() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.push(&mut memory, 0x12);
+        cpu.push(&mut memory, 0x34);
+        cpu.push(&mut memory, Status::from_byte(0x81).to_byte());
+        memory.set_byte(0x0000, 0x40);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x1234);
+        assert!(cpu.status.n);
+        assert!(cpu.status.c);
+    }
+    #[test]
+    fn test_cpu_flags() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        memory.set_byte(0x0000, 0x38); // sec
+        memory.set_byte(0x0001, 0x78); // sei
+        memory.set_byte(0x0002, 0xF8); // sed
+        memory.set_byte(0x0003, 0x18); // clc
+        cpu.execute(&mut memory);
+        cpu.execute(&mut memory);
+        cpu.execute(&mut memory);
+        assert!(cpu.status.c);
+        assert!(cpu.status.i);
+        assert!(cpu.status.d);
+        cpu.execute(&mut memory);
+        assert!(!cpu.status.c);
+    }
+    #[test]
+    fn test_cpu_ldx_zeropage_y() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.y = 0x20;
+        memory.set_byte(0x0000, 0xB6);
+        memory.set_byte(0x0001, 0xF0);
+        memory.set_byte(0x0010, 0x77); // wraps within zero page
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.x, 0x77);
+    }
+    #[test]
+    fn test_cpu_sta_absolute_y() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.acc = 0x42;
+        cpu.y = 0x01;
+        memory.set_byte(0x0000, 0x99);
+        memory.set_byte(0x0001, 0xFF);
+        memory.set_byte(0x0002, 0x20);
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x2100), 0x42);
+        assert_eq!(cpu.prgmctr, 0x0003);
+    }
+
+    #[test]
+    fn test_cmos_bra() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::with_variant(Variant::Cmos);
+        memory.set_byte(0x0000, 0x80);
+        memory.set_byte(0x0001, 0x10);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x0012);
+    }
+    #[test]
+    fn test_cmos_stz() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::with_variant(Variant::Cmos);
+        memory.set_byte(0x0000, 0x9C);
+        memory.set_byte(0x0001, 0x00);
+        memory.set_byte(0x0002, 0x30);
+        memory.set_byte(0x3000, 0xFF);
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x3000), 0x00);
+    }
+    #[test]
+    fn test_cmos_tsb_trb() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::with_variant(Variant::Cmos);
+        cpu.acc = 0x0F;
+        memory.set_byte(0x0000, 0x04); // tsb zp
+        memory.set_byte(0x0001, 0x10);
+        memory.set_byte(0x0002, 0x14); // trb zp
+        memory.set_byte(0x0003, 0x10);
+        memory.set_byte(0x0010, 0xF0);
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0010), 0xFF);
+        assert!(cpu.status.z);
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0010), 0xF0);
+        assert!(!cpu.status.z);
+    }
+    #[test]
+    fn test_cmos_rmb_smb_bbr_bbs() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::with_variant(Variant::Cmos);
+        memory.set_byte(0x0000, 0xB7); // smb3
+        memory.set_byte(0x0001, 0x10);
+        memory.set_byte(0x0002, 0x3F); // bbr3, not taken
+        memory.set_byte(0x0003, 0x10);
+        memory.set_byte(0x0004, 0x20);
+        memory.set_byte(0x0005, 0xBF); // bbs3, taken
+        memory.set_byte(0x0006, 0x10);
+        memory.set_byte(0x0007, 0x20);
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0010), 0x08);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x0005);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x0028);
+
+        memory.set_byte(0x0028, 0x37); // rmb3
+        memory.set_byte(0x0029, 0x10);
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0010), 0x00);
+    }
+    #[test]
+    fn test_cmos_lda_zeropage_indirect() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::with_variant(Variant::Cmos);
+        memory.set_byte(0x0000, 0xB2);
+        memory.set_byte(0x0001, 0x42);
+        memory.set_byte(0x0042, 0x08);
+        memory.set_byte(0x0043, 0x81);
+        memory.set_byte(0x8108, 0x55);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x55);
+    }
+    #[test]
+    fn test_cmos_jmp_indirect_page_fix() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::with_variant(Variant::Cmos);
+        memory.set_byte(0x0000, 0x6C);
+        memory.set_byte(0x0001, 0xFF);
+        memory.set_byte(0x0002, 0x10);
+        memory.set_byte(0x10FF, 0x34);
+        memory.set_byte(0x1000, 0x56);
+        memory.set_byte(0x1100, 0x12);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x1234);
+    }
+    #[test]
+    fn test_cmos_nops() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::with_variant(Variant::Cmos);
+        memory.set_byte(0x0000, 0x5C); // three bytes
+        memory.set_byte(0x0003, 0x44); // two bytes
+        memory.set_byte(0x0005, 0x03); // one byte
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x0003);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x0005);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x0006);
+    }
+    #[test]
+    fn test_nmos_ignores_cmos_opcodes() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        memory.set_byte(0x0000, 0x9C);
+        memory.set_byte(0x3000, 0xFF);
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x3000), 0xFF);
+    }
+
+    #[test]
+    fn test_cpu_lda_flags() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        memory.set_byte(0x0000, 0xA9);
+        memory.set_byte(0x0001, 0x00);
+        memory.set_byte(0x0002, 0xA9);
+        memory.set_byte(0x0003, 0x80);
+        memory.set_byte(0x0004, 0xA9);
+        memory.set_byte(0x0005, 0x01);
+        cpu.execute(&mut memory);
+        assert!(cpu.status.z);
+        assert!(!cpu.status.n);
+        cpu.execute(&mut memory);
+        assert!(!cpu.status.z);
+        assert!(cpu.status.n);
+        cpu.execute(&mut memory);
+        assert!(!cpu.status.z);
+        assert!(!cpu.status.n);
+    }
+    #[test]
+    fn test_cpu_ldx_ldy_flags() {
+        let mut cpu = CPU::new();
+        cpu.ldx(0x00);
+        assert!(cpu.status.z);
+        cpu.ldy(0xFF);
+        assert!(cpu.status.n);
+        assert!(!cpu.status.z);
+    }
+    #[test]
+    fn test_cpu_transfer_flags() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.acc = 0x90;
+        memory.set_byte(0x0000, 0xAA); // tax
+        memory.set_byte(0x0001, 0xA2); // ldx #0
+        memory.set_byte(0x0002, 0x00);
+        memory.set_byte(0x0003, 0x8A); // txa
+        cpu.execute(&mut memory);
+        assert!(cpu.status.n);
+        cpu.execute(&mut memory);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x00);
+        assert!(cpu.status.z);
+        assert!(!cpu.status.n);
+    }
+    #[test]
+    fn test_cpu_inx_wraps() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.x = 0xFF;
+        memory.set_byte(0x0000, 0xE8);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.x, 0x00);
+        assert!(cpu.status.z);
+    }
+    #[test]
+    fn test_cpu_dey_wraps() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.y = 0x00;
+        memory.set_byte(0x0000, 0x88);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.y, 0xFF);
+        assert!(cpu.status.n);
+    }
+    #[test]
+    fn test_cpu_pla_flags() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.push(&mut memory, 0x00);
+        cpu.pla(&mut memory);
+        assert!(cpu.status.z);
+    }
+    #[test]
+    fn test_status_set_compare() {
+        let mut status = Status::default();
+        status.set_compare(0x10, 0x20);
+        assert!(!status.c);
+        assert!(status.n);
+        status.set_compare(0x20, 0x10);
+        assert!(status.c);
+        assert!(!status.n);
+        assert!(!status.z);
+    }
+    #[test]
+    fn test_status_set_add_overflow() {
+        let mut status = Status::default();
+        status.set_add(0x50, 0x50, 0x00A0);
+        assert!(status.v);
+        assert!(status.n);
+        assert!(!status.c);
+        status.set_add(0xD0, 0x90, 0x0160);
+        assert!(status.v);
+        assert!(status.c);
+    }
+
+    #[test]
+    fn test_cpu_adc_decimal() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.status.d = true;
+        cpu.status.c = true;
+        cpu.acc = 0x58;
+        memory.set_byte(0x0000, 0x69);
+        memory.set_byte(0x0001, 0x46);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x05);
+        assert!(cpu.status.c);
+    }
+    #[test]
+    fn test_cpu_sbc_decimal() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.status.d = true;
+        cpu.status.c = true;
+        cpu.acc = 0x12;
+        memory.set_byte(0x0000, 0xE9);
+        memory.set_byte(0x0001, 0x21);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x91);
+        assert!(!cpu.status.c);
+    }
+    #[test]
+    fn test_decimal_zero_flag_by_variant() {
+        // 99 + 1 is 00 in bcd but the nmos part sets z from the binary sum
+        let mut nmos = CPU::new();
+        nmos.status.d = true;
+        nmos.acc = 0x99;
+        nmos.adc(0x01);
+        assert_eq!(nmos.acc, 0x00);
+        assert!(!nmos.status.z);
+
+        let mut cmos = CPU::with_variant(Variant::Cmos);
+        cmos.status.d = true;
+        cmos.acc = 0x99;
+        cmos.adc(0x01);
+        assert_eq!(cmos.acc, 0x00);
+        assert!(cmos.status.z);
+    }
+    #[test]
+    fn test_decimal_disabled() {
+        let mut cpu = CPU::new();
+        cpu.decimal_mode = DecimalMode::Disabled;
+        cpu.status.d = true;
+        cpu.acc = 0x09;
+        cpu.adc(0x01);
+        assert_eq!(cpu.acc, 0x0A);
+        cpu.status.c = true;
+        cpu.sbc(0x01);
+        assert_eq!(cpu.acc, 0x09);
+    }
+
+    #[test]
+    fn test_cpu_irq_line() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.prgmctr = 0x0400;
+        memory.set_byte(0x0400, 0xEA);
+        memory.set_byte(0xFFFE, 0x00);
+        memory.set_byte(0xFFFF, 0x80);
+        memory.set_byte(0x8000, 0x40); // rti
+
+        cpu.status.i = true;
+        cpu.set_irq(true);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x0401); // masked
+
+        cpu.prgmctr = 0x0400;
+        cpu.status.i = false;
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x8000);
+        assert_eq!(memory.get_byte(0x01FD) & 0x10, 0x00); // b clear for hardware interrupts
+        assert!(cpu.status.i);
+
+        cpu.set_irq(false);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x0400);
+        assert!(!cpu.status.i);
+    }
+    #[test]
+    fn test_cpu_nmi_edge() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.status.i = true;
+        memory.set_byte(0xFFFA, 0x00);
+        memory.set_byte(0xFFFB, 0x70);
+        memory.set_byte(0x7000, 0xEA);
+        memory.set_byte(0x7001, 0xEA);
+
+        cpu.set_nmi(true);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x7000);
+
+        // holding the line does not retrigger
+        cpu.set_nmi(true);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x7001);
+
+        cpu.set_nmi(false);
+        cpu.set_nmi(true);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.prgmctr, 0x7000);
+    }
+    #[test]
+    fn test_cmos_brk_clears_decimal() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::with_variant(Variant::Cmos);
+        cpu.status.d = true;
+        memory.set_byte(0x0000, 0x00);
+        cpu.execute(&mut memory);
+        assert!(!cpu.status.d);
+        assert_eq!(memory.get_byte(0x01FD), 0x38);
+    }
+    #[test]
+    fn test_cpu_cycles_page_cross() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.x = 0x01;
+        // lda $20FE,x then lda $20FF,x
+        memory.set_byte(0x0000, 0xBD);
+        memory.set_byte(0x0001, 0xFE);
+        memory.set_byte(0x0002, 0x20);
+        memory.set_byte(0x0003, 0xBD);
+        memory.set_byte(0x0004, 0xFF);
+        memory.set_byte(0x0005, 0x20);
+        assert_eq!(cpu.step(&mut memory), 4);
+        assert_eq!(cpu.step(&mut memory), 5);
+        assert_eq!(cpu.cycles, 9);
+    }
+    #[test]
+    fn test_cpu_cycles_store_fixed() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.x = 0x01;
+        // sta $20FF,x never gets the page crossing discount
+        memory.set_byte(0x0000, 0x9D);
+        memory.set_byte(0x0001, 0xFF);
+        memory.set_byte(0x0002, 0x20);
+        memory.set_byte(0x0003, 0x9D);
+        memory.set_byte(0x0004, 0x00);
+        memory.set_byte(0x0005, 0x20);
+        assert_eq!(cpu.step(&mut memory), 5);
+        assert_eq!(cpu.step(&mut memory), 5);
+    }
+    #[test]
+    fn test_cpu_cycles_branch() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        // bne not taken
+        cpu.status.z = true;
+        memory.set_byte(0x0000, 0xD0);
+        memory.set_byte(0x0001, 0x10);
+        assert_eq!(cpu.step(&mut memory), 2);
+
+        // taken within the page
+        cpu.status.z = false;
+        memory.set_byte(0x0002, 0xD0);
+        memory.set_byte(0x0003, 0x10);
+        assert_eq!(cpu.step(&mut memory), 3);
+        assert_eq!(cpu.prgmctr, 0x0014);
+
+        // taken into the next page
+        cpu.prgmctr = 0x00F0;
+        memory.set_byte(0x00F0, 0xD0);
+        memory.set_byte(0x00F1, 0x20);
+        assert_eq!(cpu.step(&mut memory), 4);
+        assert_eq!(cpu.prgmctr, 0x0112);
+    }
+    #[test]
+    fn test_cpu_run_for_cycles() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        // a page of nops, 2 cycles each
+        for address in 0x0000..0x0100 {
+            memory.set_byte(address, 0xEA);
+        }
+        assert_eq!(cpu.run_for_cycles(&mut memory, 10), 10);
+        assert_eq!(cpu.prgmctr, 0x0005);
+        // can only stop on an instruction boundary
+        assert_eq!(cpu.run_for_cycles(&mut memory, 3), 4);
+        assert_eq!(cpu.prgmctr, 0x0007);
+        assert_eq!(cpu.cycles, 14);
+    }
+    #[test]
+    fn test_cpu_cycles_interrupt() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        memory.set_byte(0xFFFE, 0x00);
+        memory.set_byte(0xFFFF, 0x04);
+        cpu.set_irq(true);
+        assert_eq!(cpu.step(&mut memory), 7);
+        assert_eq!(cpu.prgmctr, 0x0400);
+    }
+    #[test]
+    fn test_cmos_cycles() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::with_variant(Variant::Cmos);
+        // jmp ($0200) takes 6 cycles instead of 5
+        memory.set_byte(0x0000, 0x6C);
+        memory.set_byte(0x0001, 0x00);
+        memory.set_byte(0x0002, 0x02);
+        memory.set_byte(0x0200, 0x00);
+        memory.set_byte(0x0201, 0x03);
+        assert_eq!(cpu.step(&mut memory), 6);
+
+        // decimal adc costs one more cycle
+        cpu.status.d = true;
+        memory.set_byte(0x0300, 0x69);
+        memory.set_byte(0x0301, 0x01);
+        assert_eq!(cpu.step(&mut memory), 3);
+
+        // bra is always taken
+        memory.set_byte(0x0302, 0x80);
+        memory.set_byte(0x0303, 0x02);
+        assert_eq!(cpu.step(&mut memory), 3);
+        assert_eq!(cpu.prgmctr, 0x0306);
+    }
+    #[test]
+    fn test_cpu_stack_wraparound() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.stkptr = 0x00;
+        cpu.push(&mut memory, 0x12);
+        assert_eq!(memory.get_byte(0x0100), 0x12);
+        assert_eq!(cpu.stkptr, 0xFF);
+        cpu.push(&mut memory, 0x34);
+        assert_eq!(memory.get_byte(0x01FF), 0x34);
+
+        assert_eq!(cpu.pull(&mut memory), 0x34);
+        assert_eq!(cpu.pull(&mut memory), 0x12);
+        assert_eq!(cpu.stkptr, 0x00);
+        // nothing is reported unless asked for
+        assert_eq!(cpu.take_stack_event(), None);
+    }
+    #[test]
+    fn test_cpu_txs_stays_in_page_one() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.x = 0x80;
+        cpu.acc = 0x42;
+        memory.set_byte(0x0000, 0x9A); // txs
+        memory.set_byte(0x0001, 0x48); // pha
+        cpu.execute(&mut memory);
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0180), 0x42);
+        assert_eq!(memory.get_byte(0x0080), 0x00);
+        assert_eq!(cpu.stkptr, 0x7F);
+    }
+    #[test]
+    fn test_cpu_stack_diagnostics() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.stack_diagnostics = true;
+        cpu.prgmctr = 0x0400;
+
+        cpu.pull(&mut memory);
+        assert_eq!(
+            cpu.take_stack_event(),
+            Some(StackEvent::Underflow { pc: 0x0400 })
+        );
+        assert_eq!(cpu.take_stack_event(), None);
+
+        cpu.push(&mut memory, 0x00);
+        assert_eq!(
+            cpu.take_stack_event(),
+            Some(StackEvent::Overflow { pc: 0x0400 })
+        );
+    }
+    #[test]
+    fn test_opcode_table() {
+        let official = |table: &[Opcode; 256]| table.iter().filter(|op| !op.illegal).count();
+        assert_eq!(official(&NMOS_OPCODES), 151);
+        assert_eq!(official(&CMOS_OPCODES), 212);
+
+        let tay = opcode(Variant::Nmos, 0xA8);
+        assert_eq!(tay.mnemonic, Mnemonic::Tay);
+        assert_eq!(tay.cycles, 2);
+
+        let lda = opcode(Variant::Nmos, 0xB5);
+        assert_eq!(
+            (lda.mnemonic, lda.mode, lda.bytes),
+            (Mnemonic::Lda, Mode::ZeroPageX, 2)
+        );
+
+        let bbs = opcode(Variant::Cmos, 0xFF);
+        assert_eq!((bbs.mnemonic, bbs.bytes), (Mnemonic::Bbs(7), 3));
+        assert!(opcode(Variant::Nmos, 0xFF).illegal);
+    }
+    #[test]
+    fn test_disassemble() {
+        let mut memory = MEMORY::new();
+        let program = [
+            0xA9, 0x10, // lda #$10
+            0x9D, 0x00, 0x20, // sta $2000,x
+            0xB1, 0x80, // lda ($80),y
+            0x6C, 0xFC, 0xFF, // jmp ($FFFC)
+            0xD0, 0xF4, // bne $0000
+            0x0A, // asl a
+            0xA7, 0x10, // lax $10 or smb2 $10
+        ];
+        for (address, byte) in program.iter().enumerate() {
+            memory.set_byte(address as u16, *byte);
+        }
+
+        let mut address = 0x0000;
+        let mut listing = Vec::new();
+        for _ in 0..7 {
+            let (text, bytes) = disassemble(Variant::Nmos, &memory, address);
+            listing.push(text);
+            address += bytes as u16;
+        }
+        assert_eq!(
+            listing,
+            [
+                "LDA #$10",
+                "STA $2000,X",
+                "LDA ($80),Y",
+                "JMP ($FFFC)",
+                "BNE $0000",
+                "ASL A",
+                "*LAX $10"
+            ]
+        );
+        assert_eq!(
+            disassemble(Variant::Cmos, &memory, 0x000D),
+            (String::from("SMB2 $10"), 2)
+        );
+    }
+    #[test]
+    fn test_addressing_zero_page_wraps() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.x = 0xFF;
+        cpu.y = 0x02;
+        memory.set_byte(0x007F, 0x11);
+        memory.set_byte(0x0001, 0x22);
+        memory.set_byte(0x0200, 0xB5); // lda $80,x
+        memory.set_byte(0x0201, 0x80);
+        memory.set_byte(0x0202, 0xB6); // ldx $FF,y
+        memory.set_byte(0x0203, 0xFF);
+        cpu.prgmctr = 0x0200;
+
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x11);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.x, 0x22);
+    }
+    #[test]
+    fn test_addressing_zero_page_pointer_wraps() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        // the pointer at $FF takes its high byte from $00
+        memory.set_byte(0x00FF, 0x34);
+        memory.set_byte(0x0000, 0x12);
+        memory.set_byte(0x1234, 0x56);
+        memory.set_byte(0x1235, 0x78);
+        memory.set_byte(0x0200, 0xA1); // lda ($FE,x)
+        memory.set_byte(0x0201, 0xFE);
+        memory.set_byte(0x0202, 0xB1); // lda ($FF),y
+        memory.set_byte(0x0203, 0xFF);
+        cpu.prgmctr = 0x0200;
+        cpu.x = 0x01;
+        cpu.y = 0x01;
+
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x56);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x78);
+        // neither register is touched by the addressing
+        assert_eq!((cpu.x, cpu.y), (0x01, 0x01));
+    }
+    #[test]
+    fn test_addressing_wraps_at_top_of_memory() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        memory.set_byte(0x0000, 0x9A);
+        memory.set_byte(0x0001, 0xBC);
+        memory.set_byte(0x0200, 0xBD); // lda $FFFF,x
+        memory.set_byte(0x0201, 0xFF);
+        memory.set_byte(0x0202, 0xFF);
+        memory.set_byte(0x0203, 0x91); // sta ($10),y
+        memory.set_byte(0x0204, 0x10);
+        memory.set_byte(0x0010, 0xFF);
+        memory.set_byte(0x0011, 0xFF);
+        cpu.prgmctr = 0x0200;
+        cpu.x = 0x01;
+        cpu.y = 0x02;
+
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x9A);
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0001), 0x9A);
+        assert_eq!(cpu.prgmctr, 0x0205);
+    }
+    #[test]
+    fn test_cpu_store_modes() {
+        // opcode, operand bytes, effective address with x = 0x04 and y = 0x08
+        let stores: [(Byte, &[Byte], u16); 13] = [
+            (0x85, &[0x40], 0x0040),       // sta zp
+            (0x95, &[0xFE], 0x0002),       // sta zp,x
+            (0x8D, &[0x34, 0x12], 0x1234), // sta abs
+            (0x9D, &[0x34, 0x12], 0x1238), // sta abs,x
+            (0x99, &[0x34, 0x12], 0x123C), // sta abs,y
+            (0x81, &[0x2C], 0x3000),       // sta (zp,x)
+            (0x91, &[0x30], 0x3008),       // sta (zp),y
+            (0x86, &[0x40], 0x0040),       // stx zp
+            (0x96, &[0xFC], 0x0004),       // stx zp,y
+            (0x8E, &[0x34, 0x12], 0x1234), // stx abs
+            (0x84, &[0x40], 0x0040),       // sty zp
+            (0x94, &[0xFE], 0x0002),       // sty zp,x
+            (0x8C, &[0x34, 0x12], 0x1234), // sty abs
+        ];
+
+        for (opcode, operand, address) in stores {
+            let mut memory = MEMORY::new();
+            let mut cpu = CPU::new();
+            // pointer at $30 for both indirect modes
+            memory.set_byte(0x0030, 0x00);
+            memory.set_byte(0x0031, 0x30);
+            cpu.prgmctr = 0x0200;
+            cpu.acc = 0xA5;
+            cpu.x = 0x04;
+            cpu.y = 0x08;
+            memory.set_byte(0x0200, opcode);
+            for (i, byte) in operand.iter().enumerate() {
+                memory.set_byte(0x0201 + i as u16, *byte);
+            }
+
+            cpu.execute(&mut memory);
+            let expected = match opcode {
+                0x86 | 0x96 | 0x8E => cpu.x,
+                0x84 | 0x94 | 0x8C => cpu.y,
+                _ => cpu.acc,
+            };
+            assert_eq!(memory.get_byte(address), expected, "opcode {:02X}", opcode);
+            assert_eq!(memory.get_byte(0x0200), opcode, "opcode {:02X}", opcode);
+            assert_eq!(
+                cpu.prgmctr,
+                0x0201 + operand.len() as u16,
+                "opcode {:02X}",
+                opcode
+            );
+        }
+    }
+    #[test]
+    fn test_undocumented_off_by_default() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        memory.set_byte(0x0000, 0xA7); // lax $10
+        memory.set_byte(0x0001, 0x10);
+        memory.set_byte(0x0010, 0x42);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x00);
+        assert_eq!(cpu.prgmctr, 0x0002);
+    }
+    #[test]
+    fn test_undocumented_loads_and_stores() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.undocumented = true;
+        memory.set_byte(0x0010, 0x8F);
+        let program = [
+            0xA7, 0x10, // lax $10
+            0x87, 0x11, // sax $11
+            0xCB, 0x0F, // sbx #$0F
+        ];
+        for (address, byte) in program.iter().enumerate() {
+            memory.set_byte(address as u16, *byte);
+        }
+
+        cpu.execute(&mut memory);
+        assert_eq!((cpu.acc, cpu.x), (0x8F, 0x8F));
+        assert!(cpu.status.n);
+
+        cpu.acc = 0xF0;
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0011), 0x80);
+
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.x, 0x71);
+        assert!(cpu.status.c);
+        assert_eq!(cpu.prgmctr, 0x0006);
+    }
+    #[test]
+    fn test_undocumented_read_modify_write() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.undocumented = true;
+        let program = [
+            0x07, 0x10, // slo $10
+            0x27, 0x11, // rla $11
+            0x47, 0x12, // sre $12
+            0x67, 0x13, // rra $13
+            0xC7, 0x14, // dcp $14
+            0xE7, 0x15, // isc $15
+        ];
+        for (address, byte) in program.iter().enumerate() {
+            memory.set_byte(address as u16, *byte);
+        }
+        memory.set_byte(0x0010, 0x81);
+        memory.set_byte(0x0011, 0x40);
+        memory.set_byte(0x0012, 0x03);
+        memory.set_byte(0x0013, 0x04);
+        memory.set_byte(0x0014, 0x43);
+        memory.set_byte(0x0015, 0x0F);
+
+        cpu.acc = 0x01;
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0010), 0x02);
+        assert_eq!(cpu.acc, 0x03);
+        assert!(cpu.status.c);
+
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0011), 0x81);
+        assert_eq!(cpu.acc, 0x01);
+
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0012), 0x01);
+        assert_eq!(cpu.acc, 0x00);
+        assert!(cpu.status.c);
+
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0013), 0x82);
+        assert_eq!(cpu.acc, 0x82);
+
+        cpu.acc = 0x42;
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0014), 0x42);
+        assert!(cpu.status.z);
+        assert!(cpu.status.c);
+
+        cpu.acc = 0x20;
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0015), 0x10);
+        assert_eq!(cpu.acc, 0x10);
+    }
+    #[test]
+    fn test_undocumented_immediates() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.undocumented = true;
+        let program = [
+            0x0B, 0x80, // anc #$80
+            0x4B, 0x03, // alr #$03
+            0x6B, 0xFF, // arr #$FF
+            0x8B, 0x0F, // xaa #$0F
+            0xAB, 0xFF, // lxa #$FF
+        ];
+        for (address, byte) in program.iter().enumerate() {
+            memory.set_byte(address as u16, *byte);
+        }
+
+        cpu.acc = 0xC0;
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x80);
+        assert!(cpu.status.c && cpu.status.n);
+
+        cpu.acc = 0x07;
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x01);
+        assert!(cpu.status.c);
+
+        // carry in becomes bit 7, c from bit 6, v from bit 6 xor bit 5
+        cpu.acc = 0x80;
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0xC0);
+        assert!(cpu.status.c && cpu.status.v);
+
+        cpu.acc = 0x00;
+        cpu.x = 0xFF;
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x0E);
+
+        cpu.unstable.lxa_magic = 0x00;
+        cpu.acc = 0x5A;
+        cpu.execute(&mut memory);
+        assert_eq!((cpu.acc, cpu.x), (0x5A, 0x5A));
+    }
+    #[test]
+    fn test_undocumented_sh_stores() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.undocumented = true;
+        cpu.acc = 0xFF;
+        cpu.x = 0xFF;
+        cpu.y = 0x01;
+        memory.set_byte(0x0000, 0x9F); // sha $1200,y
+        memory.set_byte(0x0001, 0x00);
+        memory.set_byte(0x0002, 0x12);
+        memory.set_byte(0x0003, 0x9E); // shx $12FF,y
+        memory.set_byte(0x0004, 0xFF);
+        memory.set_byte(0x0005, 0x12);
+        memory.set_byte(0x0006, 0x9B); // tas $1200,y
+        memory.set_byte(0x0007, 0x00);
+        memory.set_byte(0x0008, 0x12);
+
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x1201), 0x13);
+
+        // crossing into page $13 stores to page x & $13 instead
+        cpu.x = 0x05;
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x0100), 0x01);
+        assert_eq!(memory.get_byte(0x1300), 0x00);
+
+        cpu.acc = 0x0F;
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.stkptr, 0x05);
+        assert_eq!(memory.get_byte(0x1201), 0x01);
+
+        // without the and the register goes in unchanged
+        cpu.unstable.sh_and_high = false;
+        cpu.prgmctr = 0x0000;
+        cpu.execute(&mut memory);
+        assert_eq!(memory.get_byte(0x1201), 0x05);
+    }
+    #[test]
+    fn test_undocumented_las_and_nops() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.undocumented = true;
+        memory.set_byte(0x0000, 0xBB); // las $2000,y
+        memory.set_byte(0x0001, 0x00);
+        memory.set_byte(0x0002, 0x20);
+        memory.set_byte(0x0003, 0x1C); // nop $2000,x
+        memory.set_byte(0x0004, 0x00);
+        memory.set_byte(0x0005, 0x20);
+        memory.set_byte(0x0006, 0x80); // nop #$00
+        memory.set_byte(0x0008, 0x04); // nop $00
+        memory.set_byte(0x000A, 0x1A); // nop
+        memory.set_byte(0x2000, 0x3C);
+
+        cpu.execute(&mut memory);
+        assert_eq!((cpu.acc, cpu.x, cpu.stkptr), (0x3C, 0x3C, 0x3C));
+
+        assert_eq!(cpu.step(&mut memory), 4);
+        assert_eq!(cpu.prgmctr, 0x0006);
+        assert_eq!(cpu.step(&mut memory), 2);
+        assert_eq!(cpu.step(&mut memory), 3);
+        assert_eq!(cpu.step(&mut memory), 2);
+        assert_eq!(cpu.prgmctr, 0x000B);
+    }
+    #[test]
+    fn test_undocumented_jam_halts() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.undocumented = true;
+        memory.set_byte(0x0000, 0xEA);
+        memory.set_byte(0x0001, 0x02); // jam
+        memory.set_byte(0xFFFC, 0x00);
+        memory.set_byte(0xFFFD, 0x04);
+
+        cpu.run_for_cycles(&mut memory, 100);
+        assert!(cpu.halted);
+        assert_eq!(cpu.prgmctr, 0x0001);
+
+        // not even an nmi gets through
+        cpu.set_nmi(true);
+        assert_eq!(cpu.step(&mut memory), 0);
+        assert_eq!(cpu.prgmctr, 0x0001);
+
+        cpu.reset(&memory);
+        assert!(!cpu.halted);
+        assert_eq!(cpu.prgmctr, 0x0400);
+    }
+    #[test]
+    fn test_cmos_stp_halts() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::with_variant(Variant::Cmos);
+        memory.set_byte(0x0000, 0xDB);
+        cpu.execute(&mut memory);
+        assert!(cpu.halted);
+        assert_eq!(cpu.prgmctr, 0x0000);
+    }
+    #[test]
+    fn test_illegal_opcode_nop() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        memory.set_byte(0x0000, 0x0F); // slo $1234, three bytes
+        memory.set_byte(0x0001, 0x34);
+        memory.set_byte(0x0002, 0x12);
+        assert_eq!(cpu.try_execute(&mut memory), Ok(None));
+        assert_eq!(cpu.prgmctr, 0x0003);
+        assert_eq!(cpu.cycles, 6);
+    }
+    #[test]
+    fn test_illegal_opcode_halt() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.illegal_opcodes = IllegalOpcodePolicy::Halt;
+        cpu.prgmctr = 0x0200;
+        memory.set_byte(0x0200, 0x02);
+        let error = ExecError::IllegalOpcode {
+            pc: 0x0200,
+            opcode: 0x02,
+        };
+
+        assert_eq!(cpu.try_execute(&mut memory), Err(error));
+        assert!(cpu.halted);
+        assert_eq!(cpu.fault, Some(error));
+        assert_eq!(cpu.prgmctr, 0x0200);
+        assert_eq!(cpu.try_execute(&mut memory), Ok(None));
+        assert_eq!(error.to_string(), "illegal opcode $02 at $0200");
+    }
+    #[test]
+    fn test_illegal_opcode_error() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.illegal_opcodes = IllegalOpcodePolicy::Error;
+        memory.set_byte(0x0000, 0xEA);
+        memory.set_byte(0x0001, 0xFF);
+
+        assert_eq!(cpu.try_execute(&mut memory), Ok(None));
+        assert_eq!(
+            cpu.try_execute(&mut memory),
+            Err(ExecError::IllegalOpcode {
+                pc: 0x0001,
+                opcode: 0xFF
+            })
+        );
+        assert!(!cpu.halted);
+        assert_eq!(cpu.prgmctr, 0x0001);
+        assert_eq!(cpu.cycles, 2);
+    }
+    #[test]
+    fn test_illegal_opcode_error_stops_running() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        cpu.illegal_opcodes = IllegalOpcodePolicy::Error;
+        memory.set_byte(0x0000, 0x02);
+        let error = ExecError::IllegalOpcode {
+            pc: 0x0000,
+            opcode: 0x02,
+        };
+
+        assert_eq!(cpu.run_for_cycles(&mut memory, 10), 0);
+        assert_eq!(cpu.fault, Some(error));
+        assert_eq!(cpu.prgmctr, 0x0000);
+
+        cpu.fault = None;
+        assert_eq!(cpu.step(&mut memory), 0);
+        assert_eq!(cpu.fault, Some(error));
+        assert!(!cpu.halted);
+    }
+    #[test]
+    fn test_illegal_opcode_callback() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::new();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&seen);
+        cpu.illegal_opcodes = IllegalOpcodePolicy::Callback(Box::new(move |error| {
+            log.borrow_mut().push(*error);
+        }));
+        memory.set_byte(0x0000, 0x04); // nop $00
+        memory.set_byte(0x0002, 0xA9); // lda #$01
+        memory.set_byte(0x0003, 0x01);
+
+        cpu.execute(&mut memory);
+        cpu.execute(&mut memory);
+        assert_eq!(cpu.acc, 0x01);
+        assert_eq!(
+            *seen.borrow(),
+            [ExecError::IllegalOpcode {
+                pc: 0x0000,
+                opcode: 0x04
+            }]
+        );
+    }
+    #[test]
+    fn test_cmos_spare_opcodes_are_not_illegal() {
+        let mut memory = MEMORY::new();
+        let mut cpu = CPU::with_variant(Variant::Cmos);
+        cpu.illegal_opcodes = IllegalOpcodePolicy::Error;
+        memory.set_byte(0x0000, 0x02);
+        assert_eq!(cpu.try_execute(&mut memory), Ok(None));
+        assert_eq!(cpu.prgmctr, 0x0002);
+    }
+    // ram with a read-to-clear register at $D000, like a device status flag
+    struct LatchBus {
+        ram: MEMORY,
+        latch: Byte,
+        writes: Vec<(u16, Byte)>,
+    }
+
+    impl Bus for LatchBus {
+        fn read(&mut self, address: u16) -> Byte {
+            match address {
+                0xD000 => std::mem::take(&mut self.latch),
+                _ => self.ram.get_byte(address),
+            }
+        }
+
+        fn write(&mut self, address: u16, value: Byte) {
+            self.writes.push((address, value));
+            self.ram.set_byte(address, value);
+        }
+
+        fn peek(&self, address: u16) -> Byte {
+            match address {
+                0xD000 => self.latch,
+                _ => self.ram.get_byte(address),
+            }
+        }
+    }
+
+    #[test]
+    fn test_cpu_custom_bus() {
+        let mut bus = LatchBus {
+            ram: MEMORY::new(),
+            latch: 0x80,
+            writes: Vec::new(),
+        };
+        let mut cpu = CPU::new();
+        let program = [
+            0xAD, 0x00, 0xD0, // lda $D000
+            0x8D, 0x00, 0x02, // sta $0200
+            0xAD, 0x00, 0xD0, // lda $D000
+        ];
+        for (address, byte) in program.iter().enumerate() {
+            bus.ram.set_byte(address as u16, *byte);
+        }
+
+        // peeking leaves the latch alone
+        assert_eq!(disassemble(cpu.variant, &bus, 0x0000).0, "LDA $D000");
+        assert_eq!(bus.peek(0xD000), 0x80);
+
+        cpu.execute(&mut bus);
+        assert_eq!(cpu.acc, 0x80);
+        cpu.execute(&mut bus);
+        assert_eq!(bus.writes, [(0x0200, 0x80)]);
+        cpu.execute(&mut bus);
+        assert_eq!(cpu.acc, 0x00);
+    }
+    #[test]
+    fn test_memory_map_ram_and_mirrors() {
+        let mut map = MemoryMap::new();
+        map.ram(0x0000..=0x07FF);
+        map.mirror(0x0800..=0x1FFF, 0x0000..=0x07FF);
+
+        map.write(0x0801, 0x42);
+        assert_eq!(map.read(0x0001), 0x42);
+        assert_eq!(map.read(0x1801), 0x42);
+        assert_eq!(map.region(0x1FFF), (Region::Ram, 0x07FF));
+    }
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn test_memory_map_broken_mirrors() {
+        let mut map = MemoryMap::new();
+        map.mirror(0x0000..=0x00FF, 0x0100..=0x01FF);
+        map.mirror(0x0100..=0x01FF, 0x0000..=0x00FF);
+        map.mirror(0x0200..=0x02FF, 0x0200..=0x02FF);
+        map.mirror(0x0400..=0x04FF, 0x0010..=0x0000);
+        map.map(0x0500..=0x05FF, Region::Mirror(0x0010..=0x0000));
+
+        assert_eq!(map.region(0x0010), (Region::Unmapped, 0x0010));
+        assert_eq!(map.region(0x0200), (Region::Unmapped, 0x0200));
+        assert_eq!(map.region(0x0410), (Region::Unmapped, 0x0410));
+        assert_eq!(map.region(0x0510), (Region::Unmapped, 0x0510));
+        map.peek(0x0010);
+    }
+    #[test]
+    fn test_memory_map_rom() {
+        let mut map = MemoryMap::new();
+        map.rom(0xFFFC..=0xFFFF, &[0x00, 0x80]);
+
+        map.write(0xFFFC, 0x12);
+        assert_eq!(map.read(0xFFFC), 0x00);
+        assert!(map.take_rom_writes().is_empty());
+
+        map.rom_writes = RomWrites::Report;
+        map.write(0xFFFD, 0x34);
+        assert_eq!(map.read(0xFFFD), 0x80);
+        assert_eq!(map.take_rom_writes(), [(0xFFFD, 0x34)]);
+        assert!(map.take_rom_writes().is_empty());
+    }
+    #[test]
+    fn test_memory_map_rom_from_file() {
+        let path = std::env::temp_dir().join("emu6502_memory_map_rom.bin");
+        let path = path.to_str().unwrap();
+        fs::write(path, [0x00, 0xF0, 0x00, 0xF0]).unwrap();
+
+        let mut map = MemoryMap::new();
+        map.ram(0x0000..=0x7FFF);
+        map.rom_from_file(0xFFFC, path).unwrap();
+        assert_eq!(map.region(0xFFFF), (Region::Rom, 0xFFFF));
+        assert!(map.rom_from_file(0xFFFE, path).is_err());
+        assert!(map.rom_from_file(0xF000, "no/such/rom.bin").is_err());
+        fs::remove_file(path).unwrap();
+
+        // the reset vector comes out of the rom
+        let mut cpu = CPU::new();
+        map.rom(0xF000..=0xF001, &[0xA9, 0x07]);
+        cpu.reset(&map);
+        assert_eq!(cpu.prgmctr, 0xF000);
+        cpu.execute(&mut map);
+        assert_eq!(cpu.acc, 0x07);
+    }
+    #[test]
+    fn test_memory_map_open_bus() {
+        let mut map = MemoryMap::new();
+        map.ram(0x0000..=0x00FF);
+        map.unmapped(0x0080..=0x008F);
+
+        map.write(0x0010, 0x5A);
+        assert_eq!(map.read(0x0010), 0x5A);
+        // unmapped reads see the last byte on the bus, writes go nowhere
+        assert_eq!(map.read(0x4000), 0x5A);
+        map.write(0x0080, 0x11);
+        assert_eq!(map.peek(0x0080), 0x11);
+        assert_eq!(map.memory.get_byte(0x0080), 0x00);
+        assert_eq!(map.read(0x0081), 0x11);
+    }
+    // 16K banks each filled with their own bank number
+    fn numbered_banks(count: usize) -> Vec<Byte> {
+        (0..count)
+            .flat_map(|bank| vec![bank as Byte; 0x4000])
+            .collect()
+    }
+
+    #[test]
+    fn test_mapper_latch() {
+        let mut map = MemoryMap::new();
+        let rom = (0..4).flat_map(|bank| vec![bank as Byte; 0x2000]).collect();
+        map.map_banked(0xA000..=0xBFFF, Box::new(Latch::new(rom, 0x2000)));
+
+        assert_eq!(map.read(0xA000), 0);
+        map.write(0xA123, 2);
+        assert_eq!(map.read(0xBFFF), 2);
+        // bank numbers wrap around the image
+        map.write(0xA000, 5);
+        assert_eq!(map.read(0xA000), 1);
+        assert_eq!(map.mappers()[0].banks(), [("bank", 1)]);
+    }
+    #[test]
+    fn test_mapper_nrom_and_cnrom() {
+        let mut map = MemoryMap::new();
+        map.map_banked(0x8000..=0xFFFF, Box::new(Nrom::new(numbered_banks(1))));
+        // one 16K bank shows up at both $8000 and $C000
+        assert_eq!(map.read(0x8000), 0);
+        assert_eq!(map.read(0xC000), 0);
+        map.write(0x8000, 0x55);
+        assert_eq!(map.read(0x8000), 0);
+
+        let mut map = MemoryMap::new();
+        map.map_banked(0x8000..=0xFFFF, Box::new(Cnrom::new(numbered_banks(2), 4)));
+        assert_eq!((map.read(0x8000), map.read(0xC000)), (0, 1));
+        map.write(0x8000, 0x03);
+        assert_eq!(map.mappers()[0].banks()[2], ("chr $0000", 3));
+    }
+    #[test]
+    fn test_mapper_uxrom() {
+        let mut map = MemoryMap::new();
+        map.map_banked(0x8000..=0xFFFF, Box::new(Uxrom::new(numbered_banks(8))));
+        assert_eq!((map.read(0x8000), map.read(0xFFFF)), (0, 7));
+        map.write(0xC000, 0x05);
+        assert_eq!((map.read(0xBFFF), map.read(0xC000)), (5, 7));
+        assert_eq!(
+            map.mappers()[0].banks(),
+            [("prg $8000", 5), ("prg $C000", 7)]
+        );
+    }
+    #[test]
+    fn test_mapper_mmc1() {
+        let mut map = MemoryMap::new();
+        map.map_banked(0x8000..=0xFFFF, Box::new(Mmc1::new(numbered_banks(8))));
+        let serial = |map: &mut MemoryMap, address: u16, value: Byte| {
+            for bit in 0..5 {
+                map.write(address, value >> bit);
+            }
+        };
+
+        // the last bank starts out fixed at $C000
+        assert_eq!((map.read(0x8000), map.read(0xC000)), (0, 7));
+        serial(&mut map, 0xE000, 0x03);
+        assert_eq!((map.read(0x8000), map.read(0xC000)), (3, 7));
+
+        // fix the first bank at $8000 and switch $C000
+        serial(&mut map, 0x8000, 0x08);
+        assert_eq!((map.read(0x8000), map.read(0xC000)), (0, 3));
+
+        // 32K mode ignores the low bit of the bank
+        serial(&mut map, 0x8000, 0x00);
+        assert_eq!((map.read(0x8000), map.read(0xC000)), (2, 3));
+
+        // a write with bit 7 set resets the shift register and the prg mode
+        map.write(0x8000, 0x01);
+        map.write(0x8000, 0x80);
+        assert_eq!((map.read(0x8000), map.read(0xC000)), (3, 7));
+    }
+    #[test]
+    fn test_mapper_mmc1_bad_snapshot() {
+        let path = std::env::temp_dir().join("emu6502_mmc1.dump");
+        let path = path.to_str().unwrap();
+        // a shift register nine bits in, which no run of writes can leave behind
+        let mut snapshot = vec![0x00; 0x10000];
+        snapshot.extend([6, 0x1F, 9, 0x0C, 0, 0, 0]);
+        fs::write(path, snapshot).unwrap();
+
+        let mut map = MemoryMap::new();
+        map.map_banked(0x8000..=0xFFFF, Box::new(Mmc1::new(numbered_banks(8))));
+        map.load_snapshot(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        // it comes back empty, so the next five writes load a register
+        for bit in 0..5 {
+            map.write(0xE000, 0x03 >> bit);
+        }
+        assert_eq!((map.read(0x8000), map.read(0xC000)), (3, 7));
+    }
+    #[test]
+    fn test_mapper_from_ines() {
+        let mut image = vec![b'N', b'E', b'S', 0x1A, 2, 1, 0x10, 0x00];
+        image.resize(16, 0);
+        image.extend(numbered_banks(2));
+        assert_eq!(from_ines(&image).unwrap().name(), "mmc1");
+
+        image[6] = 0x40;
+        assert!(from_ines(&image).is_err());
+        assert!(from_ines(&image[..20]).is_err());
+        assert!(from_ines(b"not a rom").is_err());
+    }
+    #[test]
+    fn test_memory_map_snapshot() {
+        let path = std::env::temp_dir().join("emu6502_snapshot.dump");
+        let path = path.to_str().unwrap();
+
+        let mut map = MemoryMap::new();
+        map.ram(0x0000..=0x7FFF);
+        map.map_banked(0x8000..=0xFFFF, Box::new(Uxrom::new(numbered_banks(4))));
+        map.write(0x0200, 0x42);
+        map.write(0x8000, 0x02);
+        map.save_snapshot(path).unwrap();
+
+        let mut restored = MemoryMap::new();
+        restored.ram(0x0000..=0x7FFF);
+        restored.map_banked(0x8000..=0xFFFF, Box::new(Uxrom::new(numbered_banks(4))));
+        restored.load_snapshot(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(restored.read(0x0200), 0x42);
+        assert_eq!(restored.read(0x8000), 2);
+        assert!(restored.load_snapshot("no/such/snapshot").is_err());
+    }
+    // a countdown timer at two addresses, writing the low byte starts it and reading
+    // it back acknowledges the interrupt
+    struct CountdownTimer {
+        count: Byte,
+        running: bool,
+        fired: bool,
+        nmi: bool,
+    }
+
+    impl Device for CountdownTimer {
+        fn name(&self) -> &str {
+            "countdown"
+        }
+
+        fn read(&mut self, offset: u16) -> Byte {
+            let value = self.peek(offset);
+            if offset == 1 {
+                self.fired = false;
+            }
+            value
+        }
+
+        fn write(&mut self, offset: u16, value: Byte) {
+            match offset {
+                0 => {
+                    self.count = value;
+                    self.running = true;
+                }
+                _ => self.nmi = value != 0,
+            }
+        }
+
+        fn peek(&self, offset: u16) -> Byte {
+            match offset {
+                0 => self.count,
+                _ => (self.fired as Byte) << 7,
+            }
+        }
+
+        fn tick(&mut self) {
+            if self.running {
+                self.count -= 1;
+                if self.count == 0 {
+                    self.running = false;
+                    self.fired = true;
+                }
+            }
+        }
+
+        fn irq(&self) -> bool {
+            self.fired
+        }
+
+        fn nmi(&self) -> bool {
+            self.nmi
+        }
+    }
+
+    fn countdown_map() -> MemoryMap {
+        let mut map = MemoryMap::new();
+        map.ram(0x0000..=0xFFFF);
+        let timer = CountdownTimer {
+            count: 0,
+            running: false,
+            fired: false,
+            nmi: false,
+        };
+        assert_eq!(map.map_device(0xD000..=0xD00F, Box::new(timer)), 0);
+        map
+    }
+
+    #[test]
+    fn test_device_registers() {
+        let mut map = countdown_map();
+        map.write(0xD000, 0x05);
+        assert_eq!(map.region(0xD001), (Region::Device(0), 0x0001));
+        assert_eq!(map.read(0xD000), 0x05);
+        assert_eq!(map.devices()[0].name(), "countdown");
+
+        map.tick(5);
+        assert!(map.irq());
+        // peeking leaves the flag up, reading clears it
+        assert_eq!(map.peek(0xD001), 0x80);
+        assert!(map.device::<CountdownTimer>(0).unwrap().fired);
+        assert_eq!(map.read(0xD001), 0x80);
+        assert!(!map.irq());
+        assert_eq!(map.read(0xD001), 0x00);
+
+        map.device_mut::<CountdownTimer>(0).unwrap().count = 0x42;
+        assert_eq!(map.peek(0xD000), 0x42);
+        assert!(map.device::<CountdownTimer>(1).is_none());
+    }
+    #[test]
+    fn test_device_ticks_with_cpu_cycles() {
+        let mut map = countdown_map();
+        let mut cpu = CPU::new();
+        map.write(0xD000, 10);
+        map.write(0x0000, 0xEA); // nop
+        map.write(0x0001, 0xEA); // nop
+
+        assert_eq!(cpu.step(&mut map), 2);
+        assert_eq!(cpu.step(&mut map), 2);
+        assert_eq!(map.peek(0xD000), 6);
+    }
+    #[test]
+    fn test_device_raises_irq() {
+        let mut map = countdown_map();
+        let mut cpu = CPU::new();
+        let program = [
+            0xA9, 0x04, // lda #$04
+            0x8D, 0x00, 0xD0, // sta $D000
+            0x58, // cli
+            0x4C, 0x06, 0x00, // jmp $0006
+        ];
+        for (address, byte) in program.iter().enumerate() {
+            map.write(address as u16, *byte);
+        }
+        map.write(0xFFFE, 0x00);
+        map.write(0xFFFF, 0x80);
+        map.write(0x8000, 0xAD); // lda $D001
+        map.write(0x8001, 0x01);
+        map.write(0x8002, 0xD0);
+        map.write(0x8003, 0x40); // rti
+        cpu.status.i = true;
+
+        cpu.execute(&mut map);
+        cpu.execute(&mut map);
+        // the timer runs out while the irq is still masked
+        assert!(map.irq());
+        assert_eq!(cpu.prgmctr, 0x0005);
+        cpu.execute(&mut map);
+        cpu.execute(&mut map);
+        assert_eq!(cpu.prgmctr, 0x8000);
+        cpu.execute(&mut map);
+        assert_eq!(cpu.acc, 0x80);
+        assert!(!map.irq());
+        cpu.execute(&mut map);
+        assert_eq!(cpu.prgmctr, 0x0006);
+        cpu.execute(&mut map);
+        assert_eq!(cpu.prgmctr, 0x0006);
+    }
+    #[test]
+    fn test_device_raises_nmi_on_edge() {
+        let mut map = countdown_map();
+        let mut cpu = CPU::new();
+        map.write(0xFFFA, 0x00);
+        map.write(0xFFFB, 0x90);
+        map.write(0x9000, 0x40); // rti
+        for address in 0x0000..=0x0002 {
+            map.write(address, 0xEA); // nop
+        }
+        cpu.status.i = true;
+
+        map.write(0xD001, 0x01);
+        cpu.execute(&mut map);
+        assert_eq!(cpu.prgmctr, 0x9000);
+        cpu.execute(&mut map);
+        // the line is still held but there is no new edge
+        assert_eq!(cpu.prgmctr, 0x0000);
+        cpu.execute(&mut map);
+        assert_eq!(cpu.prgmctr, 0x0001);
+
+        map.write(0xD001, 0x00);
+        cpu.execute(&mut map);
+        map.write(0xD001, 0x01);
+        cpu.execute(&mut map);
+        assert_eq!(cpu.prgmctr, 0x9000);
+    }
+    fn tick_device(device: &mut impl Device, cycles: u32) {
+        for _ in 0..cycles {
+            device.tick();
+        }
+    }
+
+    #[test]
+    fn test_via_ports() {
+        let mut via = Via::new();
+        via.write(via::DDRA, 0xF0);
+        via.write(via::ORA, 0xAA);
+        via.set_port_a(0x05);
+        assert_eq!(via.port_a(), 0xA5);
+        assert_eq!(via.read(via::ORA), 0xA5);
+
+        // output pins read back orb even if something outside pulls them down
+        via.write(via::DDRB, 0x0F);
+        via.write(via::ORB, 0x3C);
+        via.set_port_b(0x00);
+        assert_eq!(via.port_b(), 0x0C);
+        assert_eq!(via.read(via::ORB), 0x0C);
+        via.set_port_b(0xF0);
+        assert_eq!(via.read(via::ORB), 0xFC);
+
+        // latching keeps the inputs seen at the ca1 edge
+        via.write(via::ACR, 0x01);
+        via.set_ca1(false);
+        via.set_port_a(0x00);
+        assert_eq!(via.read(via::ORA_NO_HANDSHAKE), 0xA5);
+    }
+    #[test]
+    fn test_via_timer1_one_shot() {
+        let mut via = Via::new();
+        via.write(via::ACR, 0x80);
+        via.write(via::IER, Via::IRQ | Via::T1);
+        assert_eq!(via.read(via::IER), 0xC0);
+        via.write(via::T1C_L, 0x05);
+        via.write(via::T1C_H, 0x00);
+        assert_eq!(via.port_b() & 0x80, 0x00);
+
+        tick_device(&mut via, 5);
+        assert_eq!(via.peek(via::T1C_L), 0x00);
+        assert!(!via.irq());
+        tick_device(&mut via, 1);
+        assert!(via.irq());
+        assert_eq!(via.peek(via::IFR), Via::IRQ | Via::T1);
+        assert_eq!(via.port_b() & 0x80, 0x80);
+
+        // reading the low counter acknowledges it and the timer keeps counting
+        assert_eq!(via.read(via::T1C_L), 0xFF);
+        assert!(!via.irq());
+        tick_device(&mut via, 0x10000);
+        assert!(!via.irq());
+    }
+    #[test]
+    fn test_via_timer1_free_run() {
+        let mut via = Via::new();
+        via.write(via::ACR, 0xC0);
+        via.write(via::T1C_L, 0x03);
+        via.write(via::T1C_H, 0x00);
+
+        let mut fired = Vec::new();
+        let mut pb7 = Vec::new();
+        for cycle in 1..=14 {
+            via.tick();
+            if via.peek(via::IFR) & Via::T1 != 0 {
+                fired.push(cycle);
+                via.write(via::IFR, Via::T1);
+            }
+            pb7.push(via.port_b() >> 7);
+        }
+        assert_eq!(fired, [4, 9, 14]);
+        assert_eq!(pb7, [0, 0, 0, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 1]);
+        // irqs stay off until enabled
+        assert!(!via.irq());
+    }
+    #[test]
+    fn test_via_timer2() {
+        let mut via = Via::new();
+        via.write(via::IER, Via::IRQ | Via::T2);
+        via.write(via::T2C_L, 0x02);
+        via.write(via::T2C_H, 0x00);
+        tick_device(&mut via, 3);
+        assert!(via.irq());
+        via.read(via::T2C_L);
+        // one shot only
+        tick_device(&mut via, 0x10000);
+        assert!(!via.irq());
+
+        // pulse counting on pb6
+        via.write(via::ACR, 0x20);
+        via.write(via::T2C_L, 0x03);
+        via.write(via::T2C_H, 0x00);
+        tick_device(&mut via, 100);
+        assert_eq!(via.peek(via::T2C_L), 0x03);
+        for _ in 0..3 {
+            assert!(!via.irq());
+            via.set_port_b(0xBF);
+            via.set_port_b(0xFF);
+        }
+        assert!(via.irq());
+        assert_eq!(via.peek(via::T2C_L), 0x00);
+    }
+    #[test]
+    fn test_via_shift_register() {
+        let mut via = Via::new();
+        via.write(via::IER, Via::IRQ | Via::SHIFT);
+
+        // shift out under phi2
+        via.write(via::ACR, 0x18);
+        via.write(via::SR, 0xA5);
+        let mut bits = Vec::new();
+        for _ in 0..8 {
+            assert!(!via.irq());
+            via.tick();
+            bits.push(via.cb2() as Byte);
+        }
+        assert_eq!(bits, [1, 0, 1, 0, 0, 1, 0, 1]);
+        assert!(via.irq());
+        via.tick();
+        assert_eq!(via.peek(via::SR), 0xA5);
+
+        // shift in clocked by cb1, sampling cb2 on the rising edge
+        via.write(via::ACR, 0x0C);
+        via.read(via::SR);
+        assert!(!via.irq());
+        for bit in [0, 1, 1, 0, 0, 0, 1, 1] {
+            via.set_cb2(bit == 1);
+            via.set_cb1(false);
+            via.set_cb1(true);
+        }
+        assert!(via.irq());
+        assert_eq!(via.peek(via::SR), 0x63);
+    }
+    #[test]
+    fn test_via_handshake() {
+        let mut via = Via::new();
+        via.write(via::IER, Via::IRQ | Via::CA1 | Via::CA2);
+
+        // ca1 on the rising edge, ca2 as a handshake output
+        via.write(via::PCR, 0x09);
+        via.set_ca1(false);
+        assert!(!via.irq());
+        via.write(via::ORA, 0x42);
+        assert!(!via.ca2());
+        via.set_ca1(true);
+        assert!(via.irq());
+        assert!(via.ca2());
+        via.read(via::ORA);
+        assert!(!via.irq());
+
+        // pulse output lasts a single cycle, the read above left ca2 low
+        via.write(via::PCR, 0x0A);
+        via.tick();
+        via.write(via::ORA_NO_HANDSHAKE, 0x00);
+        assert!(via.ca2());
+        via.write(via::ORA, 0x00);
+        assert!(!via.ca2());
+        via.tick();
+        assert!(via.ca2());
+
+        // an independent ca2 interrupt is not cleared by port accesses
+        via.write(via::PCR, 0x02);
+        via.set_ca2(false);
+        assert!(via.irq());
+        via.read(via::ORA);
+        assert!(via.irq());
+        via.write(via::IFR, Via::CA2);
+        assert!(!via.irq());
+
+        via.write(via::PCR, 0xC0);
+        assert!(!via.cb2());
+        via.write(via::PCR, 0xE0);
+        assert!(via.cb2());
+    }
+    #[test]
+    fn test_via_interrupts_cpu() {
+        let mut map = MemoryMap::new();
+        map.ram(0x0000..=0x5FFF);
+        map.ram(0x8000..=0xFFFF);
+        map.map_device(0x6000..=0x600F, Box::new(Via::new()));
+        let program = [
+            0xA9, 0xC0, // lda #$C0
+            0x8D, 0x0E, 0x60, // sta $600E, enable t1
+            0xA9, 0x40, // lda #$40
+            0x8D, 0x0B, 0x60, // sta $600B, free run
+            0xA9, 0x20, // lda #$20
+            0x8D, 0x04, 0x60, // sta $6004
+            0x9C, 0x05, 0x60, // stz $6005
+            0x58, // cli
+            0x80, 0xFE, // bra *
+        ];
+        for (address, byte) in program.iter().enumerate() {
+            map.write(0x8000 + address as u16, *byte);
+        }
+        let handler = [
+            0xE6, 0x00, // inc $00
+            0xAD, 0x04, 0x60, // lda $6004
+            0x40, // rti
+        ];
+        for (address, byte) in handler.iter().enumerate() {
+            map.write(0x9000 + address as u16, *byte);
+        }
+        map.write(0xFFFE, 0x00);
+        map.write(0xFFFF, 0x90);
+
+        let mut cpu = CPU::with_variant(Variant::Cmos);
+        cpu.prgmctr = 0x8000;
+        cpu.status.i = true;
+        cpu.run_for_cycles(&mut map, 34 * 10 + 30);
+        assert_eq!(map.peek(0x0000), 10);
+    }
+    // 9600 baud 8n1 against a 1MHz clock, 10 bits of 104 cycles
+    const ACIA_FRAME: u32 = 1041;
+
+    #[test]
+    fn test_acia_transmit() {
+        let mut acia = Acia::new();
+        acia.write(acia::CONTROL, 0x1E);
+        acia.write(acia::COMMAND, 0x0B);
+        assert_eq!(acia.peek(acia::STATUS), Acia::TDRE);
+
+        acia.write(acia::DATA, b'H');
+        assert_eq!(acia.peek(acia::STATUS), 0x00);
+        // straight into the shift register, leaving room for the next one
+        acia.tick();
+        assert_eq!(acia.peek(acia::STATUS), Acia::TDRE);
+        acia.write(acia::DATA, b'i');
+        tick_device(&mut acia, ACIA_FRAME - 1);
+        assert!(acia.take_transmitted().is_empty());
+        acia.tick();
+        assert_eq!(acia.take_transmitted(), b"H");
+        assert_eq!(acia.peek(acia::STATUS), 0x00);
+        tick_device(&mut acia, ACIA_FRAME + 1);
+        assert_eq!(acia.take_transmitted(), b"i");
+        assert!(!acia.irq());
+
+        // nothing goes out with the transmitter off
+        acia.write(acia::COMMAND, 0x01);
+        acia.write(acia::DATA, b'!');
+        tick_device(&mut acia, 2 * ACIA_FRAME);
+        assert!(acia.take_transmitted().is_empty());
+    }
+    #[test]
+    fn test_acia_transmit_irq() {
+        let mut acia = Acia::new();
+        acia.write(acia::CONTROL, 0x1F);
+        acia.write(acia::COMMAND, 0x07);
+        acia.write(acia::DATA, b'A');
+        acia.tick();
+        assert!(acia.irq());
+        assert_eq!(acia.read(acia::STATUS), Acia::IRQ | Acia::TDRE);
+        assert!(!acia.irq());
+    }
+    #[test]
+    fn test_acia_receive() {
+        let mut acia = Acia::new();
+        acia.write(acia::CONTROL, 0x1E);
+        acia.receive(b"ok");
+        // the receiver is off until dtr is set
+        tick_device(&mut acia, 2 * ACIA_FRAME);
+        assert_eq!(acia.peek(acia::STATUS), Acia::TDRE);
+
+        acia.write(acia::COMMAND, 0x09);
+        tick_device(&mut acia, ACIA_FRAME);
+        assert_eq!(acia.peek(acia::STATUS), Acia::TDRE);
+        acia.tick();
+        assert!(acia.irq());
+        assert_eq!(acia.read(acia::STATUS), Acia::IRQ | Acia::RDRF | Acia::TDRE);
+        assert!(!acia.irq());
+        assert_eq!(acia.read(acia::DATA), b'o');
+        assert_eq!(acia.peek(acia::STATUS), Acia::TDRE);
+
+        // the second character is lost if the first is not read in time
+        acia.receive(b"!");
+        tick_device(&mut acia, 3 * ACIA_FRAME);
+        assert_eq!(acia.peek(acia::STATUS) & Acia::OVERRUN, Acia::OVERRUN);
+        assert_eq!(acia.read(acia::DATA), b'k');
+        assert_eq!(acia.read(acia::STATUS), Acia::IRQ | Acia::TDRE);
+
+        // receive interrupts can be turned off, the programmed reset turns dtr off
+        acia.write(acia::COMMAND, 0x0B);
+        acia.receive(b"?");
+        tick_device(&mut acia, 2 * ACIA_FRAME);
+        assert!(!acia.irq());
+        assert_eq!(acia.read(acia::DATA), b'?');
+        acia.write(acia::STATUS, 0x00);
+        assert_eq!(acia.peek(acia::COMMAND), 0x00);
+    }
+    #[test]
+    fn test_acia_host_bridge() {
+        let mut acia = Acia::new();
+        acia.translate_newlines = true;
+        acia.connect_input(spawn_reader(std::io::Cursor::new(b"\n".to_vec())));
+        acia.write(acia::CONTROL, 0x1F);
+        // echo mode sends back what comes in
+        acia.write(acia::COMMAND, 0x11);
+
+        let mut cycles = 0;
+        while acia.peek(acia::STATUS) & Acia::RDRF == 0 {
+            acia.tick();
+            cycles += 1;
+            assert!(cycles < 1_000_000, "nothing arrived from the reader thread");
+        }
+        assert_eq!(acia.read(acia::DATA), b'\r');
+        assert_eq!(acia.take_transmitted(), b"\n");
+
+        acia.write(acia::COMMAND, 0x09);
+        for byte in b"a\r\nb" {
+            acia.write(acia::DATA, *byte);
+            tick_device(&mut acia, 1000);
+        }
+        assert_eq!(acia.take_transmitted(), b"a\nb");
+    }
+    #[test]
+    fn test_acia_on_the_bus() {
+        let mut map = MemoryMap::new();
+        map.ram(0x0000..=0x7FFF);
+        let index = map.map_device(0x8400..=0x8403, Box::new(Acia::new()));
+        let program = [
+            0xA9, 0x1F, // lda #$1F
+            0x8D, 0x03, 0x84, // sta $8403
+            0xA9, 0x0B, // lda #$0B
+            0x8D, 0x02, 0x84, // sta $8402
+            0xA2, 0x00, // ldx #$00
+            0xBD, 0x00, 0x02, // lda $0200,x
+            0xF0, 0x0D, // beq done
+            0x8D, 0x00, 0x84, // sta $8400
+            0xAD, 0x01, 0x84, // lda $8401
+            0x29, 0x10, // and #$10
+            0xF0, 0xF9, // beq wait
+            0xE8, // inx
+            0xD0, 0xEE, // bne next
+            0x02, // done: jam
+        ];
+        for (address, byte) in program.iter().enumerate() {
+            map.write(address as u16, *byte);
+        }
+        for (address, byte) in b"hello\0".iter().enumerate() {
+            map.write(0x0200 + address as u16, *byte);
+        }
+
+        let mut cpu = CPU::new();
+        cpu.undocumented = true;
+        cpu.run_for_cycles(&mut map, 100_000);
+        assert!(cpu.halted);
+        // the last character is still on its way out
+        map.tick(ACIA_FRAME);
+        let acia = map.device_mut::<Acia>(index).unwrap();
+        assert_eq!(acia.take_transmitted(), b"hello");
+    }
+    #[test]
+    fn test_riot_ram_and_ports() {
+        let mut riot = Riot::new();
+        riot.write(riot::RAM + 0x7F, 0x42);
+        assert_eq!(riot.read(riot::RAM + 0x7F), 0x42);
+
+        riot.write(riot::IO + riot::DDRA, 0x0F);
+        riot.write(riot::IO + riot::DRA, 0x35);
+        riot.set_port_a(0xA0);
+        assert_eq!(riot.port_a(), 0xA5);
+        assert_eq!(riot.read(riot::IO + riot::DRA), 0xA5);
+
+        riot.write(riot::IO + riot::DDRB, 0xFF);
+        riot.write(riot::IO + riot::DRB, 0x81);
+        assert_eq!(riot.port_b(), 0x81);
+        assert_eq!(riot.read(riot::IO + riot::DDRB), 0xFF);
+    }
+    #[test]
+    fn test_riot_timer() {
+        let mut riot = Riot::new();
+        riot.write(riot::IO + riot::TIM8T + riot::ENABLE_IRQ, 0x02);
+        assert_eq!(riot.peek(riot::IO + riot::INTIM), 0x02);
+        tick_device(&mut riot, 1);
+        assert_eq!(riot.peek(riot::IO + riot::INTIM), 0x01);
+        tick_device(&mut riot, 8);
+        assert_eq!(riot.peek(riot::IO + riot::INTIM), 0x00);
+        tick_device(&mut riot, 7);
+        assert!(!riot.irq());
+        tick_device(&mut riot, 1);
+        assert!(riot.irq());
+        assert_eq!(riot.peek(riot::IO + riot::INSTAT), Riot::TIMER);
+
+        // after running out it counts every cycle
+        assert_eq!(riot.peek(riot::IO + riot::INTIM), 0xFF);
+        tick_device(&mut riot, 3);
+        assert_eq!(riot.peek(riot::IO + riot::INTIM), 0xFC);
+
+        // reading the timer without a3 clears the flag and the interrupt
+        assert_eq!(riot.read(riot::IO + riot::INTIM), 0xFC);
+        assert!(!riot.irq());
+        assert_eq!(riot.peek(riot::IO + riot::INSTAT), 0x00);
+
+        riot.write(riot::IO + riot::T1024T, 0x01);
+        tick_device(&mut riot, 2048);
+        assert_eq!(riot.peek(riot::IO + riot::INSTAT), Riot::TIMER);
+        assert!(!riot.irq());
+    }
+    #[test]
+    fn test_riot_pa7_edge() {
+        let mut riot = Riot::new();
+        // positive edge with the interrupt enabled
+        riot.write(riot::IO + 0x07, 0x00);
+        riot.set_port_a(0x00);
+        assert!(!riot.irq());
+        riot.set_port_a(0x80);
+        assert!(riot.irq());
+        assert_eq!(riot.read(riot::IO + riot::INSTAT), Riot::PA7);
+        assert!(!riot.irq());
+
+        // negative edge, caught when pa7 is an output too
+        riot.write(riot::IO + 0x06, 0x00);
+        riot.set_port_a(0x00);
+        assert!(riot.irq());
+        riot.read(riot::IO + riot::INSTAT);
+        riot.write(riot::IO + riot::DDRA, 0x80);
+        riot.write(riot::IO + riot::DRA, 0x80);
+        assert!(!riot.irq());
+        riot.write(riot::IO + riot::DRA, 0x00);
+        assert!(riot.irq());
+    }
+    #[test]
+    fn test_rriot() {
+        let mut rom = vec![0xEA; 1024];
+        rom[0x3FF] = 0x1C;
+        let mut rriot = Rriot::new(&rom);
+        assert_eq!(rriot.read(rriot::ROM + 0x3FF), 0x1C);
+        rriot.write(rriot::ROM, 0x00);
+        assert_eq!(rriot.read(rriot::ROM), 0xEA);
+
+        rriot.write(rriot::RAM + 0x3F, 0x99);
+        assert_eq!(rriot.read(rriot::RAM + 0x3F), 0x99);
+
+        rriot.write(rriot::IO + rriot::PADD, 0xF0);
+        rriot.write(rriot::IO + rriot::PAD, 0x5A);
+        rriot.set_port_a(0x0F);
+        assert_eq!(rriot.port_a(), 0x5F);
+        // a3 is ignored by the ports
+        assert_eq!(rriot.read(rriot::IO + 0x08 + rriot::PAD), 0x5F);
+
+        rriot.write(rriot::IO + rriot::TIMER + rriot::ENABLE_IRQ, 0x01);
+        tick_device(&mut rriot, 2);
+        assert!(rriot.irq());
+        assert_eq!(rriot.read(rriot::IO + rriot::FLAGS), 0x80);
+        assert_eq!(rriot.read(rriot::IO + rriot::TIMER + 2), 0xFF);
+        assert!(!rriot.irq());
+    }
+    #[test]
+    fn test_device_windows() {
+        let mut map = MemoryMap::new();
+        let rom: Vec<Byte> = (0..1024).map(|byte| byte as Byte).collect();
+        let index = map.map_device(0x1C00..=0x1FFF, Box::new(Rriot::new(&rom)));
+        map.map_device_window(0x1780..=0x17BF, index, rriot::RAM);
+        map.map_device_window(0x1700..=0x173F, index, rriot::IO);
+
+        assert_eq!(map.read(0x1C05), 0x05);
+        assert_eq!(map.region(0x17BF), (Region::Device(index), 0x43F));
+        map.write(0x1780, 0x42);
+        assert_eq!(map.read(0x1780), 0x42);
+        map.write(0x1701, 0xFF);
+        map.write(0x1700, 0x3C);
+        assert_eq!(map.device::<Rriot>(index).unwrap().port_a(), 0x3C);
+        assert_eq!(map.read(0x1710), 0x3C);
+    }
+    #[test]
+    fn test_pia_registers() {
+        let mut pia = Pia::new();
+        // the ddr sits under the data register until cr bit 2 is set
+        pia.write(pia::PORT_A, 0x0F);
+        pia.write(pia::CRA, Pia::DATA);
+        pia.write(pia::PORT_A, 0x35);
+        pia.set_port_a(0xA0);
+        assert_eq!(pia.read(pia::PORT_A), 0xA5);
+        pia.write(pia::CRA, 0x00);
+        assert_eq!(pia.read(pia::PORT_A), 0x0F);
+
+        pia.write(pia::PORT_B, 0xF0);
+        pia.write(pia::CRB, Pia::DATA);
+        pia.write(pia::PORT_B, 0x5A);
+        pia.set_port_b(0x00);
+        assert_eq!(pia.port_b(), 0x50);
+        assert_eq!(pia.read(pia::PORT_B), 0x50);
+
+        // the flag bits can't be written
+        pia.write(pia::CRB, 0xFF);
+        assert_eq!(pia.read(pia::CRB), 0x3F);
+    }
+    #[test]
+    fn test_pia_interrupts() {
+        let mut pia = Pia::new();
+        pia.write(pia::CRA, Pia::DATA | Pia::C1_IRQ_ENABLE | Pia::C1_POSITIVE);
+        pia.set_ca1(false);
+        assert_eq!(pia.peek(pia::CRA) & Pia::IRQ1, 0x00);
+        pia.set_ca1(true);
+        assert_eq!(pia.peek(pia::CRA) & Pia::IRQ1, Pia::IRQ1);
+        assert!(pia.irq());
+        pia.read(pia::PORT_A);
+        assert!(!pia.irq());
+
+        // ca2 as an interrupt input on the falling edge
+        pia.write(pia::CRA, Pia::DATA | 0x08);
+        pia.set_ca2(false);
+        assert!(pia.irq_a());
+        pia.read(pia::PORT_A);
+        assert!(!pia.irq_a());
+
+        // the flags are set even with the interrupts off
+        pia.write(pia::CRB, Pia::DATA);
+        pia.set_cb1(false);
+        pia.set_cb2(false);
+        assert_eq!(pia.peek(pia::CRB), Pia::IRQ1 | Pia::IRQ2 | Pia::DATA);
+        assert!(!pia.irq_b());
+    }
+    #[test]
+    fn test_pia_handshake() {
+        let mut pia = Pia::new();
+        // ca2 read handshake, low on reading ora and high again on the ca1 edge
+        pia.write(pia::CRA, Pia::DATA | 0x20);
+        assert!(pia.ca2());
+        pia.read(pia::PORT_A);
+        assert!(!pia.ca2());
+        pia.set_ca1(false);
+        assert!(pia.ca2());
+
+        // cb2 write pulse lasts a cycle
+        pia.write(pia::CRB, Pia::DATA | 0x28);
+        pia.read(pia::PORT_B);
+        assert!(pia.cb2());
+        pia.write(pia::PORT_B, 0x00);
+        assert!(!pia.cb2());
+        pia.tick();
+        assert!(pia.cb2());
+
+        // manual outputs
+        pia.write(pia::CRB, 0x30);
+        assert!(!pia.cb2());
+        pia.write(pia::CRB, 0x38);
+        assert!(pia.cb2());
+    }
+    #[test]
+    fn test_pia_keyboard_and_display() {
+        let mut pia = Pia::new();
+        let mut keyboard = Keyboard::new();
+        keyboard.high_bit = true;
+        keyboard.uppercase = true;
+        keyboard.translate_newlines = true;
+        keyboard.press(b"a\n");
+        pia.attach_keyboard(keyboard);
+        let mut display = Display::new();
+        display.busy_cycles = 2;
+        pia.attach_display(display);
+
+        pia.write(pia::CRA, Pia::DATA | Pia::C1_POSITIVE);
+        pia.tick();
+        assert_eq!(pia.peek(pia::CRA) & Pia::IRQ1, Pia::IRQ1);
+        assert_eq!(pia.read(pia::PORT_A), 0xC1);
+        // the next key waits for the last to be read
+        pia.tick();
+        assert_eq!(pia.read(pia::CRA) & Pia::IRQ1, Pia::IRQ1);
+        pia.tick();
+        assert_eq!(pia.read(pia::PORT_A), 0x8D);
+        pia.tick();
+        assert_eq!(pia.peek(pia::CRA) & Pia::IRQ1, 0x00);
+
+        pia.write(pia::PORT_B, 0x7F);
+        pia.write(pia::CRB, Pia::DATA);
+        pia.write(pia::PORT_B, b'A');
+        pia.tick();
+        assert_eq!(pia.read(pia::PORT_B) & 0x80, 0x80);
+        pia.tick();
+        assert_eq!(pia.read(pia::PORT_B) & 0x80, 0x00);
+        pia.write(pia::PORT_B, 0x80 | b'B');
+        let display = pia.display_mut().unwrap();
+        assert_eq!(display.take_printed(), b"AB");
+    }
+    #[test]
+    fn test_pia_keyboard_from_reader() {
+        let mut map = MemoryMap::new();
+        map.ram(0x0000..=0x7FFF);
+        let mut pia = Pia::new();
+        let mut keyboard = Keyboard::new();
+        keyboard.connect_input(spawn_reader(std::io::Cursor::new(b"hi".to_vec())));
+        pia.attach_keyboard(keyboard);
+        pia.attach_display(Display::new());
+        let index = map.map_device(0xD010..=0xD013, Box::new(pia));
+        // copy each key to the display until a second one arrives
+        let program = [
+            0xA9, 0x04, // lda #$04
+            0x8D, 0x11, 0xD0, // sta $D011
+            0xA9, 0x7F, // lda #$7F
+            0x8D, 0x12, 0xD0, // sta $D012, ddrb
+            0x8D, 0x13, 0xD0, // sta $D013, orb
+            0xAD, 0x11, 0xD0, // lda $D011
+            0x10, 0xFB, // bpl
+            0xAD, 0x10, 0xD0, // lda $D010
+            0x8D, 0x12, 0xD0, // sta $D012
+            0xE8, // inx
+            0xE0, 0x02, // cpx #$02
+            0xD0, 0xF0, // bne
+            0x02, // jam
+        ];
+        for (address, byte) in program.iter().enumerate() {
+            map.write(address as u16, *byte);
+        }
+
+        let mut cpu = CPU::new();
+        cpu.undocumented = true;
+        for _ in 0..1_000_000 {
+            if cpu.halted {
+                break;
+            }
+            cpu.execute(&mut map);
+        }
+        assert!(cpu.halted);
+        let pia = map.device_mut::<Pia>(index).unwrap();
+        assert_eq!(pia.display_mut().unwrap().take_printed(), b"hi");
+    }
+    // stands in for the woz monitor, echoing keys to the display the way it does
+    fn echo_monitor() -> Vec<Byte> {
+        let mut rom = vec![0xEA; 0x100];
+        let program = [
+            0xA0, 0x7F, // ldy #$7F
+            0x8C, 0x12, 0xD0, // sty $D012
+            0xA9, 0xA7, // lda #$A7
+            0x8D, 0x11, 0xD0, // sta $D011
+            0x8D, 0x13, 0xD0, // sta $D013
+            0x58, // cli
+            0xAD, 0x11, 0xD0, // lda $D011
+            0x10, 0xFB, // bpl
+            0xAD, 0x10, 0xD0, // lda $D010
+            0x2C, 0x12, 0xD0, // bit $D012
+            0x30, 0xFB, // bmi
+            0x8D, 0x12, 0xD0, // sta $D012
+            0x4C, 0x0E, 0xFF, // jmp $FF0E
+        ];
+        rom[..program.len()].copy_from_slice(&program);
+        rom[0xFC..].copy_from_slice(&[0x00, 0xFF, 0x00, 0x00]);
+        rom
+    }
+
+    #[test]
+    fn test_apple1_echo() {
+        let mut keyboard = Keyboard::new();
+        keyboard.press(b"hi\x08\n");
+        let display = Display::new();
+        let mut apple = apple1_with(8, &echo_monitor(), keyboard, display).unwrap();
+        assert_eq!(apple.cpu.prgmctr, 0xFF00);
+
+        // each character holds the display up for a sixtieth of a second
+        apple.run_for_cycles(3 * apple1::DISPLAY_CYCLES as u64);
+        let pia = apple.bus.device_mut::<Pia>(0).unwrap();
+        let printed = pia.display_mut().unwrap().take_printed();
+        assert_eq!(printed, b"HI_");
+        apple.run_for_cycles(apple1::DISPLAY_CYCLES as u64);
+        let pia = apple.bus.device_mut::<Pia>(0).unwrap();
+        assert_eq!(pia.display_mut().unwrap().take_printed(), b"\r");
+        // the pia's irq isn't wired so cli did nothing
+        assert!(!apple.cpu.halted);
+        assert!(apple.cpu.prgmctr >= 0xFF00);
+    }
+    #[test]
+    fn test_apple1_memory() {
+        let rom = echo_monitor();
+        let mut apple = apple1_with(4, &rom, Keyboard::new(), Display::new()).unwrap();
+        apple.bus.write(0x0FFF, 0x42);
+        assert_eq!(apple.bus.read(0x0FFF), 0x42);
+        assert_eq!(apple.bus.region(0x1000).0, Region::Unmapped);
+        apple.bus.write(apple1::BASIC, 0x4C);
+        assert_eq!(apple.bus.read(apple1::BASIC), 0x4C);
+        apple.bus.write(apple1::WOZMON, 0x00);
+        assert_eq!(apple.bus.read(apple1::WOZMON), 0xA0);
+
+        let apple = apple1_with(32, &rom, Keyboard::new(), Display::new()).unwrap();
+        assert_eq!(apple.bus.region(0x7FFF).0, Region::Ram);
+        assert!(apple1_with(6, &rom, Keyboard::new(), Display::new()).is_err());
+        assert!(apple1_with(36, &rom, Keyboard::new(), Display::new()).is_err());
+        assert!(apple1_with(8, &rom[..0x80], Keyboard::new(), Display::new()).is_err());
+    }
+    // ben eater's hello world, waiting on the busy flag between each transfer
+    fn hello_world_rom() -> Vec<Byte> {
+        let mut rom = vec![0xEA; 0x8000];
+        let program = [
+            0xA2, 0xFF, // ldx #$FF
+            0x9A, // txs
+            0xA9, 0xFF, // lda #$FF
+            0x8D, 0x02, 0x60, // sta DDRB
+            0xA9, 0xE0, // lda #$E0
+            0x8D, 0x03, 0x60, // sta DDRA
+            0xA9, 0x38, // lda #$38, 8 bit two lines
+            0x20, 0x55, 0x80, // jsr lcd_instruction
+            0xA9, 0x0E, // lda #$0E, display and cursor on
+            0x20, 0x55, 0x80, // jsr lcd_instruction
+            0xA9, 0x06, // lda #$06, increment
+            0x20, 0x55, 0x80, // jsr lcd_instruction
+            0xA9, 0x01, // lda #$01, clear
+            0x20, 0x55, 0x80, // jsr lcd_instruction
+            0xA2, 0x00, // ldx #$00
+            0xBD, 0x81, 0x80, // print: lda message,x
+            0xF0, 0x07, // beq done
+            0x20, 0x6B, 0x80, // jsr print_char
+            0xE8, // inx
+            0x4C, 0x23, 0x80, // jmp print
+            0x4C, 0x2F, 0x80, // done: jmp done
+            0x48, // lcd_wait: pha
+            0xA9, 0x00, // lda #$00
+            0x8D, 0x02, 0x60, // sta DDRB
+            0xA9, 0x40, // busy: lda #RW
+            0x8D, 0x01, 0x60, // sta PORTA
+            0xA9, 0xC0, // lda #RW | E
+            0x8D, 0x01, 0x60, // sta PORTA
+            0xAD, 0x00, 0x60, // lda PORTB
+            0x29, 0x80, // and #$80
+            0xD0, 0xEF, // bne busy
+            0xA9, 0x40, // lda #RW
+            0x8D, 0x01, 0x60, // sta PORTA
+            0xA9, 0xFF, // lda #$FF
+            0x8D, 0x02, 0x60, // sta DDRB
+            0x68, // pla
+            0x60, // rts
+            0x20, 0x32, 0x80, // lcd_instruction: jsr lcd_wait
+            0x8D, 0x00, 0x60, // sta PORTB
+            0xA9, 0x00, // lda #$00
+            0x8D, 0x01, 0x60, // sta PORTA
+            0xA9, 0x80, // lda #E
+            0x8D, 0x01, 0x60, // sta PORTA
+            0xA9, 0x00, // lda #$00
+            0x8D, 0x01, 0x60, // sta PORTA
+            0x60, // rts
+            0x20, 0x32, 0x80, // print_char: jsr lcd_wait
+            0x8D, 0x00, 0x60, // sta PORTB
+            0xA9, 0x20, // lda #RS
+            0x8D, 0x01, 0x60, // sta PORTA
+            0xA9, 0xA0, // lda #RS | E
+            0x8D, 0x01, 0x60, // sta PORTA
+            0xA9, 0x20, // lda #RS
+            0x8D, 0x01, 0x60, // sta PORTA
+            0x60, // rts
+        ];
+        rom[..program.len()].copy_from_slice(&program);
+        rom[0x81..0x8F].copy_from_slice(b"Hello, world!\0");
+        rom[0x7FFC..0x7FFE].copy_from_slice(&[0x00, 0x80]);
+        rom
+    }
+
+    #[test]
+    fn test_breadboard_hello_world() {
+        let board = LcdBoard::new(LcdWiring::EightBit);
+        let mut breadboard = ben_eater_with(&hello_world_rom(), board).unwrap();
+        assert_eq!(breadboard.cpu.variant, Variant::Cmos);
+        breadboard.run_for_cycles(20_000);
+        assert_eq!(breadboard.cpu.prgmctr, 0x802F);
+
+        let board = breadboard.bus.device::<LcdBoard>(0).unwrap();
+        assert_eq!(board.lcd.lines(), ["Hello, world!   ", "                "]);
+        assert_eq!(board.lcd.cursor(), Some((0, 13)));
+        assert!(board
+            .render()
+            .starts_with("+----------------+\n|Hello, world!\x1B[4m \x1B[0m"));
+
+        // the via is decoded all the way up to the rom
+        assert_eq!(breadboard.bus.peek(0x7FF2), 0xFF);
+        assert!(ben_eater_with(&[0x00; 0x4000], LcdBoard::new(LcdWiring::EightBit)).is_err());
+    }
+    #[test]
+    fn test_breadboard_lcd_busy_flag() {
+        let mut board = LcdBoard::new(LcdWiring::EightBit);
+        board.write(via::DDRB, 0xFF);
+        board.write(via::DDRA, 0xE0);
+        // clear display then read the busy flag while it works
+        board.write(via::ORB, 0x01);
+        board.write(via::ORA, 0x80);
+        board.write(via::ORA, 0x00);
+        board.write(via::DDRB, 0x00);
+        board.write(via::ORA, 0xC0);
+        assert_eq!(board.read(via::ORB), 0x80);
+        tick_device(&mut board, 1520);
+        assert_eq!(board.read(via::ORB), 0x00);
+        board.write(via::ORA, 0x40);
+        // nobody driving the port now
+        assert_eq!(board.read(via::ORB), 0xFF);
+    }
+    #[test]
+    fn test_breadboard_lcd_four_bit() {
+        let mut board = LcdBoard::new(LcdWiring::FourBit);
+        board.write(via::DDRB, 0x7F);
+        let send = |board: &mut LcdBoard, nibble: Byte, rs: Byte| {
+            board.write(via::ORB, nibble | rs);
+            board.write(via::ORB, nibble | rs | 0x40);
+            board.write(via::ORB, nibble | rs);
+        };
+        // the first function set arrives in 8 bit mode as a single nibble
+        send(&mut board, 0x02, 0x00);
+        assert!(!board.lcd.eight_bit());
+        for byte in [0x28, 0x0C, 0x06] {
+            send(&mut board, byte >> 4, 0x00);
+            send(&mut board, byte & 0x0F, 0x00);
+        }
+        for byte in b"Hi" {
+            send(&mut board, byte >> 4, 0x10);
+            send(&mut board, byte & 0x0F, 0x10);
+        }
+        assert_eq!(board.lcd.lines()[0], "Hi              ");
+        assert_eq!(board.lcd.cursor(), None);
+
+        // reading the address counter takes two pulses, busy flag in the first
+        board.write(via::DDRB, 0x70);
+        board.write(via::ORB, 0x20);
+        board.write(via::ORB, 0x60);
+        assert_eq!(board.read(via::ORB) & 0x0F, 0x08);
+        board.write(via::ORB, 0x20);
+        board.write(via::ORB, 0x60);
+        assert_eq!(board.read(via::ORB) & 0x0F, 0x02);
+        board.write(via::ORB, 0x20);
+        tick_device(&mut board, 41);
+        board.write(via::ORB, 0x60);
+        assert_eq!(board.read(via::ORB) & 0x0F, 0x00);
+    }
+    #[test]
+    fn test_hd44780_instructions() {
+        let mut lcd = Hd44780::new(16);
+        let send = |lcd: &mut Hd44780, rs: bool, byte: Byte| {
+            lcd.update(rs, false, true, byte);
+            lcd.update(rs, false, false, byte);
+        };
+        send(&mut lcd, false, 0x38);
+        send(&mut lcd, false, 0x0F);
+        // start at the end of the first line and carry on onto the second
+        send(&mut lcd, false, 0x80 | 0x27);
+        send(&mut lcd, true, b'a');
+        send(&mut lcd, true, b'b');
+        assert_eq!(lcd.cursor(), Some((1, 1)));
+        assert_eq!(lcd.lines()[1], "b               ");
+
+        // shift everything right so column 39 comes round to the left edge
+        send(&mut lcd, false, 0x1C);
+        assert_eq!(lcd.lines(), ["a               ", " b              "]);
+        assert_eq!(lcd.cursor(), Some((1, 2)));
+        send(&mut lcd, false, 0x18);
+        send(&mut lcd, false, 0x18);
+        assert_eq!(lcd.cursor(), Some((1, 0)));
+        send(&mut lcd, false, 0x02);
+        assert_eq!(lcd.lines()[0], "                ");
+
+        // display off hides the cursor, custom characters show as ?
+        send(&mut lcd, false, 0x40);
+        send(&mut lcd, true, 0x1F);
+        send(&mut lcd, false, 0x80);
+        send(&mut lcd, true, 0x00);
+        send(&mut lcd, true, 0x5C);
+        assert!(lcd.lines()[0].starts_with("?¥"));
+        send(&mut lcd, false, 0x08);
+        assert_eq!(lcd.cursor(), None);
+        assert!(lcd.take_changed());
+        assert!(!lcd.take_changed());
+    }
+    #[test]
+    fn test_kim1_memory_map() {
+        let mut monitor = vec![0xEA; 0x800];
+        monitor[0x000] = 0x03;
+        // the 002's rom, jmp $1C00 at $1C00 and the nmi handler going through $17FA
+        monitor[0x400..0x403].copy_from_slice(&[0x4C, 0x00, 0x1C]);
+        monitor[0x410..0x413].copy_from_slice(&[0x6C, 0xFA, 0x17]);
+        monitor[0x7FA..0x7FE].copy_from_slice(&[0x10, 0x1C, 0x00, 0x1C]);
+        let mut kim = kim1_with(
+            &monitor,
+            Console::Keypad,
+            Keypad::new(),
+            Leds::new(),
+            Tty::new(),
+        )
+        .unwrap();
+        assert_eq!(kim.cpu.prgmctr, kim1::MONITOR);
+        assert_eq!(kim.bus.peek(kim1::ROM_003), 0x03);
+        assert_eq!(kim.bus.peek(0xFFFA), 0x10);
+        assert_eq!(kim.bus.devices().len(), 2);
+        assert_eq!(
+            kim.bus.region(kim1::IO_002 + 1),
+            (Region::Device(1), rriot::IO + 1)
+        );
+
+        // the first 8K repeats all the way up
+        kim.bus.write(0x0200, 0x42);
+        assert_eq!(kim.bus.peek(0xE200), 0x42);
+        kim.bus.write(kim1::RAM_003, 0x11);
+        kim.bus.write(kim1::RAM_002, 0x22);
+        assert_eq!(kim.bus.peek(kim1::RAM_003 + 0x2000), 0x11);
+        assert_eq!(kim.bus.peek(kim1::RAM_002), 0x22);
+        assert_eq!(kim.bus.peek(kim1::NMI_VECTOR + 1), 0x1C);
+        assert_eq!(kim.bus.peek(kim1::IRQ_VECTOR + 1), 0x1C);
+
+        // ST on the keypad goes back to the monitor
+        kim.bus.write(0x0300, 0x4C);
+        kim.bus.write(0x0301, 0x00);
+        kim.bus.write(0x0302, 0x03);
+        kim.cpu.prgmctr = 0x0300;
+        kim.bus
+            .device_mut::<KimBoard>(1)
+            .unwrap()
+            .keypad
+            .press(b"s");
+        kim.run_for_cycles(100);
+        assert_eq!(kim.cpu.prgmctr, kim1::MONITOR);
+
+        assert!(kim1_with(
+            &[0x00; 0x400],
+            Console::Tty,
+            Keypad::new(),
+            Leds::new(),
+            Tty::new()
+        )
+        .is_err());
+    }
+    #[test]
+    fn test_kim1_keypad_and_leds() {
+        let mut board = KimBoard::new(&[], Console::Keypad);
+        board.leds.redraw_cycles = 100;
+        // a 1 on the first digit and an F on the last
+        board.write(rriot::IO + rriot::PADD, 0x7F);
+        board.write(rriot::IO + rriot::PBDD, 0x1E);
+        board.write(rriot::IO + rriot::PBD, 0x08);
+        board.write(rriot::IO + rriot::PAD, 0x06);
+        tick_device(&mut board, 10);
+        board.write(rriot::IO + rriot::PAD, 0x00);
+        board.write(rriot::IO + rriot::PBD, 0x12);
+        board.write(rriot::IO + rriot::PAD, 0x71);
+        tick_device(&mut board, 90);
+        assert_eq!(board.leds.digits(), "1     F");
+        let screen = board.leds.render();
+        let lines: Vec<_> = screen.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with(" _ "));
+        assert!(lines[1].starts_with("  |") && lines[1].ends_with("|_ "));
+        assert!(lines[2].starts_with("  |") && lines[2].ends_with("|  "));
+
+        // keys without a place on the keypad are passed over, GO is pa1 on row 2
+        board.keypad.press(b"xg");
+        board.write(rriot::IO + rriot::PADD, 0x00);
+        board.write(rriot::IO + rriot::PBD, 0x04);
+        tick_device(&mut board, 1);
+        assert_eq!(board.read(rriot::IO + rriot::PAD), 0xFD);
+        board.write(rriot::IO + rriot::PBD, 0x00);
+        assert_eq!(board.read(rriot::IO + rriot::PAD), 0xFF);
+
+        // held down for a while, then left up as long before the next key
+        let cycles = board.keypad.press_cycles as u32;
+        board.keypad.press(b"0s");
+        tick_device(&mut board, cycles);
+        assert_eq!(board.read(rriot::IO + rriot::PAD), 0xFF);
+        tick_device(&mut board, cycles + 1);
+        assert_eq!(board.read(rriot::IO + rriot::PAD), 0xBF);
+        assert!(!board.nmi());
+        tick_device(&mut board, 2 * cycles + 1);
+        assert!(board.nmi());
+    }
+    #[test]
+    fn test_kim1_tty() {
+        let mut board = KimBoard::new(&[], Console::Tty);
+        // the jumper shows on pa0 while output 3 is selected
+        board.write(rriot::IO + rriot::PBDD, 0x1F);
+        board.write(rriot::IO + rriot::PBD, 0x07);
+        assert_eq!(board.read(rriot::IO + rriot::PAD), 0xFE);
+        board.write(rriot::IO + rriot::PBD, 0x01);
+        assert_eq!(board.read(rriot::IO + rriot::PAD), 0xFF);
+
+        // typing waits for a quiet line, then the bits come in on pa7
+        let bit = (board.tty.clock_hz / board.tty.baud) as u32;
+        board.tty.press(b"A");
+        tick_device(&mut board, 22 * bit - 1);
+        assert_eq!(board.read(rriot::IO + rriot::PAD), 0xFF);
+        tick_device(&mut board, 1 + bit / 2);
+        assert_eq!(board.read(rriot::IO + rriot::PAD), 0x7F);
+        let mut byte = 0x00;
+        for n in 0..8 {
+            tick_device(&mut board, bit);
+            byte |= (board.read(rriot::IO + rriot::PAD) >> 7) << n;
+        }
+        assert_eq!(byte, b'A');
+        tick_device(&mut board, 2 * bit);
+        // the board echoes what is typed straight to the printer
+        assert_eq!(board.tty.take_printed(), b"A");
+
+        // and the printer reads pb0
+        board.tty.echo = false;
+        let frame = [0, 1, 1, 0, 1, 0, 0, 1, 0, 1, 1];
+        for level in frame.iter().chain(&frame) {
+            board.write(rriot::IO + rriot::PBD, *level);
+            tick_device(&mut board, bit);
+        }
+        assert_eq!(board.tty.take_printed(), b"KK");
     }
 }
@@ -0,0 +1,350 @@
+// bank switching, a mapper owns a rom image and decides which part of it the cpu sees
+//
+// addresses handed to a mapper are offsets from the start of the range it is mapped at
+use crate::{Byte, Word};
+
+pub trait Mapper {
+    fn name(&self) -> &str;
+    fn read(&self, offset: Word) -> Byte;
+    // writes into rom space usually land in the bank registers
+    fn write(&mut self, offset: Word, value: Byte);
+    // the bank selected for each window, for showing to people
+    fn banks(&self) -> Vec<(&'static str, usize)>;
+    // the raw bank registers, for snapshots
+    fn registers(&self) -> Vec<Byte>;
+    fn set_registers(&mut self, registers: &[Byte]);
+}
+
+const KB: usize = 1024;
+
+// picks byte offset out of bank number bank, wrapping around the image like the
+// unconnected address lines do
+fn banked(rom: &[Byte], bank_size: usize, bank: usize, offset: usize) -> Byte {
+    if rom.is_empty() {
+        return 0x00;
+    }
+    rom[(bank * bank_size + offset % bank_size) % rom.len()]
+}
+
+fn bank_count(rom: &[Byte], bank_size: usize) -> usize {
+    (rom.len() / bank_size).max(1)
+}
+
+// the simplest board there is, any write latches the bank number for the window
+pub struct Latch {
+    rom: Vec<Byte>,
+    bank_size: usize,
+    bank: usize,
+}
+
+impl Latch {
+    pub fn new(rom: Vec<Byte>, bank_size: usize) -> Self {
+        Self {
+            rom,
+            bank_size,
+            bank: 0,
+        }
+    }
+}
+
+impl Mapper for Latch {
+    fn name(&self) -> &str {
+        "latch"
+    }
+
+    fn read(&self, offset: Word) -> Byte {
+        banked(&self.rom, self.bank_size, self.bank, offset as usize)
+    }
+
+    fn write(&mut self, _offset: Word, value: Byte) {
+        self.bank = value as usize % bank_count(&self.rom, self.bank_size);
+    }
+
+    fn banks(&self) -> Vec<(&'static str, usize)> {
+        vec![("bank", self.bank)]
+    }
+
+    fn registers(&self) -> Vec<Byte> {
+        vec![self.bank as Byte]
+    }
+
+    fn set_registers(&mut self, registers: &[Byte]) {
+        if let Some(bank) = registers.first() {
+            self.bank = *bank as usize;
+        }
+    }
+}
+
+// nes mapper 0, 16K or 32K of prg rom and no registers
+pub struct Nrom {
+    prg: Vec<Byte>,
+}
+
+impl Nrom {
+    pub fn new(prg: Vec<Byte>) -> Self {
+        Self { prg }
+    }
+}
+
+impl Mapper for Nrom {
+    fn name(&self) -> &str {
+        "nrom"
+    }
+
+    // a 16K image shows up twice
+    fn read(&self, offset: Word) -> Byte {
+        banked(&self.prg, 32 * KB, 0, offset as usize)
+    }
+
+    fn write(&mut self, _offset: Word, _value: Byte) {}
+
+    fn banks(&self) -> Vec<(&'static str, usize)> {
+        let last = bank_count(&self.prg, 16 * KB) - 1;
+        vec![("prg $8000", 0), ("prg $C000", last)]
+    }
+
+    fn registers(&self) -> Vec<Byte> {
+        Vec::new()
+    }
+
+    fn set_registers(&mut self, _registers: &[Byte]) {}
+}
+
+// nes mapper 2, a switchable 16K bank at $8000 and the last bank fixed at $C000
+pub struct Uxrom {
+    prg: Vec<Byte>,
+    bank: usize,
+}
+
+impl Uxrom {
+    pub fn new(prg: Vec<Byte>) -> Self {
+        Self { prg, bank: 0 }
+    }
+}
+
+impl Mapper for Uxrom {
+    fn name(&self) -> &str {
+        "uxrom"
+    }
+
+    fn read(&self, offset: Word) -> Byte {
+        let bank = match offset < 0x4000 {
+            true => self.bank,
+            false => bank_count(&self.prg, 16 * KB) - 1,
+        };
+        banked(&self.prg, 16 * KB, bank, offset as usize)
+    }
+
+    fn write(&mut self, _offset: Word, value: Byte) {
+        self.bank = value as usize % bank_count(&self.prg, 16 * KB);
+    }
+
+    fn banks(&self) -> Vec<(&'static str, usize)> {
+        let last = bank_count(&self.prg, 16 * KB) - 1;
+        vec![("prg $8000", self.bank), ("prg $C000", last)]
+    }
+
+    fn registers(&self) -> Vec<Byte> {
+        vec![self.bank as Byte]
+    }
+
+    fn set_registers(&mut self, registers: &[Byte]) {
+        if let Some(bank) = registers.first() {
+            self.bank = *bank as usize;
+        }
+    }
+}
+
+// nes mapper 3, fixed prg like nrom, writes pick the 8K chr bank the ppu sees
+pub struct Cnrom {
+    prg: Vec<Byte>,
+    chr_banks: usize,
+    chr: usize,
+}
+
+impl Cnrom {
+    pub fn new(prg: Vec<Byte>, chr_banks: usize) -> Self {
+        Self {
+            prg,
+            chr_banks: chr_banks.max(1),
+            chr: 0,
+        }
+    }
+}
+
+impl Mapper for Cnrom {
+    fn name(&self) -> &str {
+        "cnrom"
+    }
+
+    fn read(&self, offset: Word) -> Byte {
+        banked(&self.prg, 32 * KB, 0, offset as usize)
+    }
+
+    fn write(&mut self, _offset: Word, value: Byte) {
+        self.chr = value as usize % self.chr_banks;
+    }
+
+    fn banks(&self) -> Vec<(&'static str, usize)> {
+        let last = bank_count(&self.prg, 16 * KB) - 1;
+        vec![
+            ("prg $8000", 0),
+            ("prg $C000", last),
+            ("chr $0000", self.chr),
+        ]
+    }
+
+    fn registers(&self) -> Vec<Byte> {
+        vec![self.chr as Byte]
+    }
+
+    fn set_registers(&mut self, registers: &[Byte]) {
+        if let Some(chr) = registers.first() {
+            self.chr = *chr as usize;
+        }
+    }
+}
+
+// nes mapper 1, registers are loaded a bit at a time through a five bit shift register
+pub struct Mmc1 {
+    prg: Vec<Byte>,
+    shift: Byte,
+    count: Byte,
+    control: Byte,
+    chr0: Byte,
+    chr1: Byte,
+    prg_bank: Byte,
+}
+
+impl Mmc1 {
+    pub fn new(prg: Vec<Byte>) -> Self {
+        Self {
+            prg,
+            shift: 0,
+            count: 0,
+            // powers up with the last bank fixed at $C000
+            control: 0x0C,
+            chr0: 0,
+            chr1: 0,
+            prg_bank: 0,
+        }
+    }
+
+    // the 16K banks at $8000 and $C000
+    fn prg_banks(&self) -> (usize, usize) {
+        let bank = (self.prg_bank & 0x0F) as usize;
+        let last = bank_count(&self.prg, 16 * KB) - 1;
+        match (self.control >> 2) & 0x03 {
+            0 | 1 => (bank & !1, bank | 1),
+            2 => (0, bank),
+            _ => (bank, last),
+        }
+    }
+
+    // the 4K chr banks at $0000 and $1000
+    fn chr_banks(&self) -> (usize, usize) {
+        match self.control & 0x10 {
+            0 => ((self.chr0 & !1) as usize, (self.chr0 | 1) as usize),
+            _ => (self.chr0 as usize, self.chr1 as usize),
+        }
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn name(&self) -> &str {
+        "mmc1"
+    }
+
+    fn read(&self, offset: Word) -> Byte {
+        let (low, high) = self.prg_banks();
+        let bank = match offset < 0x4000 {
+            true => low,
+            false => high,
+        };
+        banked(&self.prg, 16 * KB, bank, offset as usize)
+    }
+
+    fn write(&mut self, offset: Word, value: Byte) {
+        if value & 0x80 != 0 {
+            self.shift = 0;
+            self.count = 0;
+            self.control |= 0x0C;
+            return;
+        }
+
+        self.shift |= (value & 0x01) << self.count;
+        self.count += 1;
+        if self.count == 5 {
+            match (offset >> 13) & 0x03 {
+                0 => self.control = self.shift,
+                1 => self.chr0 = self.shift,
+                2 => self.chr1 = self.shift,
+                _ => self.prg_bank = self.shift,
+            }
+            self.shift = 0;
+            self.count = 0;
+        }
+    }
+
+    fn banks(&self) -> Vec<(&'static str, usize)> {
+        let (prg_low, prg_high) = self.prg_banks();
+        let (chr_low, chr_high) = self.chr_banks();
+        vec![
+            ("prg $8000", prg_low),
+            ("prg $C000", prg_high),
+            ("chr $0000", chr_low),
+            ("chr $1000", chr_high),
+        ]
+    }
+
+    fn registers(&self) -> Vec<Byte> {
+        vec![
+            self.shift,
+            self.count,
+            self.control,
+            self.chr0,
+            self.chr1,
+            self.prg_bank,
+        ]
+    }
+
+    fn set_registers(&mut self, registers: &[Byte]) {
+        if let [shift, count, control, chr0, chr1, prg_bank] = *registers {
+            // a shift register that could never have been seen comes back empty
+            (self.shift, self.count) = match count {
+                0..=4 => (shift, count),
+                _ => (0, 0),
+            };
+            self.control = control;
+            self.chr0 = chr0;
+            self.chr1 = chr1;
+            self.prg_bank = prg_bank;
+        }
+    }
+}
+
+// builds the mapper for an ines image, to be mapped at $8000-$FFFF
+pub fn from_ines(image: &[Byte]) -> Result<Box<dyn Mapper>, String> {
+    if image.len() < 16 || &image[0..4] != b"NES\x1A" {
+        return Err(String::from("not an ines image"));
+    }
+
+    let prg_size = image[4] as usize * 16 * KB;
+    let chr_banks = image[5] as usize;
+    let trainer = if image[6] & 0x04 != 0 { 512 } else { 0 };
+    let number = (image[7] & 0xF0) | (image[6] >> 4);
+
+    let start = 16 + trainer;
+    let prg = match image.get(start..start + prg_size) {
+        Some(prg) => prg.to_vec(),
+        None => return Err(String::from("prg rom is cut short")),
+    };
+
+    match number {
+        0 => Ok(Box::new(Nrom::new(prg))),
+        1 => Ok(Box::new(Mmc1::new(prg))),
+        2 => Ok(Box::new(Uxrom::new(prg))),
+        3 => Ok(Box::new(Cnrom::new(prg, chr_banks))),
+        _ => Err(format!("mapper {} is not supported", number)),
+    }
+}
@@ -0,0 +1,266 @@
+// a machine's address space declared as ram, rom, mirrors, holes and devices
+use std::{fs, io, ops::RangeInclusive};
+
+use crate::bus::Bus;
+use crate::cpu::MEMORY;
+use crate::device::Device;
+use crate::mapper::Mapper;
+use crate::{Byte, Word, MEMORY_RANGE};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Region {
+    Ram,
+    Rom,
+    Mirror(RangeInclusive<Word>), // repeats the given range across the region
+    Unmapped,                     // reads float to whatever was last on the bus
+    Banked(usize),                // handed to the mapper with this index
+    Device(usize),                // handed to the device with this index
+    DeviceWindow(usize, Word),    // handed to a device starting at the given offset
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RomWrites {
+    #[default]
+    Ignore,
+    Report, // kept until take_rom_writes is called
+}
+
+pub struct MemoryMap {
+    pub memory: MEMORY, // backing store for ram and rom, indexed by address
+    pub rom_writes: RomWrites,
+    regions: Vec<(RangeInclusive<Word>, Region)>,
+    mappers: Vec<Box<dyn Mapper>>,
+    devices: Vec<Box<dyn Device>>,
+    reported: Vec<(Word, Byte)>,
+    open_bus: Byte,
+}
+
+impl MemoryMap {
+    // starts with nothing mapped
+    pub fn new() -> Self {
+        Self {
+            memory: MEMORY::new(),
+            rom_writes: RomWrites::default(),
+            regions: Vec::new(),
+            mappers: Vec::new(),
+            devices: Vec::new(),
+            reported: Vec::new(),
+            open_bus: 0x00,
+        }
+    }
+
+    // later declarations win where ranges overlap
+    pub fn map(&mut self, range: RangeInclusive<Word>, region: Region) {
+        self.regions.push((range, region));
+    }
+
+    pub fn ram(&mut self, range: RangeInclusive<Word>) {
+        self.map(range, Region::Ram);
+    }
+
+    // copies the image in at the start of range, which becomes read only
+    pub fn rom(&mut self, range: RangeInclusive<Word>, image: &[Byte]) {
+        for (offset, byte) in image.iter().take(range.len()).enumerate() {
+            self.memory.set_byte(range.start() + offset as Word, *byte);
+        }
+        self.map(range, Region::Rom);
+    }
+
+    // maps a rom image from a file at start, sized to the file
+    pub fn rom_from_file(&mut self, start: Word, path: &str) -> io::Result<()> {
+        let image = fs::read(path)?;
+        match (start as usize).checked_add(image.len()) {
+            Some(end) if !image.is_empty() && end <= MEMORY_RANGE => {
+                self.rom(start..=(end - 1) as Word, &image);
+                Ok(())
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} does not fit at ${:04X}", path, start),
+            )),
+        }
+    }
+
+    // e.g. mirror(0x0800..=0x1FFF, 0x0000..=0x07FF) for 2K of ram seen four times
+    pub fn mirror(&mut self, range: RangeInclusive<Word>, of: RangeInclusive<Word>) {
+        // a mirror of nothing is a hole
+        match of.is_empty() {
+            true => self.unmapped(range),
+            false => self.map(range, Region::Mirror(of)),
+        }
+    }
+
+    pub fn unmapped(&mut self, range: RangeInclusive<Word>) {
+        self.map(range, Region::Unmapped);
+    }
+
+    // hands a range over to a mapper, returning its index in mappers
+    pub fn map_banked(&mut self, range: RangeInclusive<Word>, mapper: Box<dyn Mapper>) -> usize {
+        self.mappers.push(mapper);
+        let index = self.mappers.len() - 1;
+        self.map(range, Region::Banked(index));
+        index
+    }
+
+    pub fn mappers(&self) -> &[Box<dyn Mapper>] {
+        &self.mappers
+    }
+
+    // hands a range over to a device, returning its index in devices
+    pub fn map_device(&mut self, range: RangeInclusive<Word>, device: Box<dyn Device>) -> usize {
+        self.devices.push(device);
+        let index = self.devices.len() - 1;
+        self.map(range, Region::Device(index));
+        index
+    }
+
+    // maps another range onto a device already in devices, for chips whose ram, rom and
+    // registers are decoded at different places
+    pub fn map_device_window(&mut self, range: RangeInclusive<Word>, index: usize, offset: Word) {
+        self.map(range, Region::DeviceWindow(index, offset));
+    }
+
+    pub fn devices(&self) -> &[Box<dyn Device>] {
+        &self.devices
+    }
+
+    // the device with this index, if it is a T
+    pub fn device<T: Device>(&self, index: usize) -> Option<&T> {
+        self.devices.get(index)?.as_ref().as_any().downcast_ref()
+    }
+
+    pub fn device_mut<T: Device>(&mut self, index: usize) -> Option<&mut T> {
+        self.devices
+            .get_mut(index)?
+            .as_mut()
+            .as_any_mut()
+            .downcast_mut()
+    }
+
+    // what an address ends up at after following mirrors
+    pub fn region(&self, address: Word) -> (Region, Word) {
+        let mut target = address;
+        // a mirror of a mirror is fine, but a chain longer than there are regions has
+        // gone round in a circle
+        for _ in 0..=self.regions.len() {
+            let found = self
+                .regions
+                .iter()
+                .rev()
+                .find(|(range, _)| range.contains(&target));
+            match found {
+                Some((range, Region::Mirror(of))) if !of.is_empty() => {
+                    let offset = (target - range.start()) as usize % of.len();
+                    target = of.start() + offset as Word;
+                }
+                Some((range, Region::Banked(index))) => {
+                    return (Region::Banked(*index), target - range.start());
+                }
+                Some((range, Region::Device(index))) => {
+                    return (Region::Device(*index), target - range.start());
+                }
+                Some((range, Region::DeviceWindow(index, offset))) => {
+                    let offset = offset.wrapping_add(target - range.start());
+                    return (Region::Device(*index), offset);
+                }
+                Some((_, Region::Mirror(_))) => break,
+                Some((_, region)) => return (region.clone(), target),
+                None => return (Region::Unmapped, target),
+            }
+        }
+        (Region::Unmapped, address)
+    }
+
+    // rom writes seen since the last call, when rom_writes is Report
+    pub fn take_rom_writes(&mut self) -> Vec<(Word, Byte)> {
+        std::mem::take(&mut self.reported)
+    }
+
+    // all 64K of the backing store followed by each mapper's registers, a snapshot
+    // without any mapper is the same as a plain memory dump
+    pub fn save_snapshot(&self, path: &str) -> io::Result<()> {
+        let mut snapshot = self.memory.data.to_vec();
+        for mapper in self.mappers.iter() {
+            let registers = mapper.registers();
+            snapshot.push(registers.len() as Byte);
+            snapshot.extend(registers);
+        }
+        fs::write(path, snapshot)
+    }
+
+    pub fn load_snapshot(&mut self, path: &str) -> io::Result<()> {
+        let snapshot = fs::read(path)?;
+        if snapshot.len() < MEMORY_RANGE {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{} is too short to be a snapshot", path),
+            ));
+        }
+
+        self.memory.data.copy_from_slice(&snapshot[..MEMORY_RANGE]);
+        let mut rest = &snapshot[MEMORY_RANGE..];
+        for mapper in self.mappers.iter_mut() {
+            let Some((&length, registers)) = rest.split_first() else {
+                break;
+            };
+            let length = (length as usize).min(registers.len());
+            mapper.set_registers(&registers[..length]);
+            rest = &registers[length..];
+        }
+        Ok(())
+    }
+}
+
+impl Bus for MemoryMap {
+    fn read(&mut self, address: Word) -> Byte {
+        self.open_bus = match self.region(address) {
+            (Region::Device(index), offset) => self.devices[index].read(offset),
+            _ => self.peek(address),
+        };
+        self.open_bus
+    }
+
+    fn write(&mut self, address: Word, value: Byte) {
+        self.open_bus = value;
+        match self.region(address) {
+            (Region::Ram, target) => self.memory.set_byte(target, value),
+            (Region::Rom, _) if self.rom_writes == RomWrites::Report => {
+                self.reported.push((address, value))
+            }
+            (Region::Banked(index), offset) => self.mappers[index].write(offset, value),
+            (Region::Device(index), offset) => self.devices[index].write(offset, value),
+            _ => {}
+        }
+    }
+
+    fn peek(&self, address: Word) -> Byte {
+        match self.region(address) {
+            (Region::Ram | Region::Rom, target) => self.memory.get_byte(target),
+            (Region::Banked(index), offset) => self.mappers[index].read(offset),
+            (Region::Device(index), offset) => self.devices[index].peek(offset),
+            _ => self.open_bus,
+        }
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        for device in self.devices.iter_mut() {
+            for _ in 0..cycles {
+                device.tick();
+            }
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.devices.iter().any(|device| device.irq())
+    }
+
+    fn nmi(&self) -> bool {
+        self.devices.iter().any(|device| device.nmi())
+    }
+}
+
+impl Default for MemoryMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,682 @@
+// opcode metadata shared by the decoder, the disassembler and the cycle counter
+use std::fmt;
+
+use crate::bus::Bus;
+use crate::cpu::Variant;
+use crate::{Byte, Word};
+
+use Mnemonic::*;
+use Mode::*;
+
+#[rustfmt::skip]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mnemonic {
+    Adc, And, Asl, Bcc, Bcs, Beq, Bit, Bmi, Bne, Bpl, Brk, Bvc, Bvs, Clc,
+    Cld, Cli, Clv, Cmp, Cpx, Cpy, Dec, Dex, Dey, Eor, Inc, Inx, Iny, Jmp,
+    Jsr, Lda, Ldx, Ldy, Lsr, Nop, Ora, Pha, Php, Pla, Plp, Rol, Ror, Rti,
+    Rts, Sbc, Sec, Sed, Sei, Sta, Stx, Sty, Tax, Tay, Tsx, Txa, Txs, Tya,
+
+    // 65c02 additions, the bit number is part of rmb, smb, bbr and bbs
+    Bra, Phx, Phy, Plx, Ply, Stz, Trb, Tsb, Wai, Stp,
+    Rmb(Byte), Smb(Byte), Bbr(Byte), Bbs(Byte),
+
+    // undocumented nmos opcodes
+    Slo, Rla, Sre, Rra, Sax, Lax, Dcp, Isc, Anc, Alr, Arr, Xaa, Lxa, Sbx,
+    Las, Sha, Shx, Shy, Tas, Jam,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,                // jmp (abs)
+    IndexedIndirect,         // (zp,x)
+    IndirectIndexed,         // (zp),y
+    Relative,                // branches
+    ZeroPageIndirect,        // (zp), 65c02 only
+    AbsoluteIndexedIndirect, // jmp (abs,x), 65c02 only
+    ZeroPageRelative,        // bbr and bbs, 65c02 only
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Opcode {
+    pub mnemonic: Mnemonic,
+    pub mode: Mode,
+    pub bytes: Byte,  // including the opcode
+    pub cycles: Byte, // before page crossing and branch penalties
+    pub illegal: bool,
+}
+
+impl Mode {
+    pub const fn bytes(self) -> Byte {
+        match self {
+            Implied | Accumulator => 1,
+            Absolute
+            | AbsoluteX
+            | AbsoluteY
+            | Indirect
+            | AbsoluteIndexedIndirect
+            | ZeroPageRelative => 3,
+            _ => 2,
+        }
+    }
+}
+
+impl Opcode {
+    // indexed reads take an extra cycle when the index carries into the next page
+    pub fn page_penalty(&self, variant: Variant) -> bool {
+        let indexed = matches!(self.mode, AbsoluteX | AbsoluteY | IndirectIndexed);
+        indexed
+            && match self.mnemonic {
+                // stores and read-modify-writes always spend the fixup cycle
+                Sta | Stz | Inc | Dec | Slo | Rla | Sre | Rra | Dcp | Isc | Sha | Shx | Shy
+                | Tas => false,
+                // except shifts and rotates on the 65c02
+                Asl | Lsr | Rol | Ror => variant == Variant::Cmos,
+                _ => true,
+            }
+    }
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rmb(bit) => write!(f, "RMB{}", bit),
+            Smb(bit) => write!(f, "SMB{}", bit),
+            Bbr(bit) => write!(f, "BBR{}", bit),
+            Bbs(bit) => write!(f, "BBS{}", bit),
+            _ => write!(f, "{}", format!("{:?}", self).to_uppercase()),
+        }
+    }
+}
+
+const fn op(mnemonic: Mnemonic, mode: Mode, cycles: Byte) -> Opcode {
+    Opcode {
+        mnemonic,
+        mode,
+        bytes: mode.bytes(),
+        cycles,
+        illegal: false,
+    }
+}
+
+const fn illegal(mnemonic: Mnemonic, mode: Mode, cycles: Byte) -> Opcode {
+    Opcode {
+        illegal: true,
+        ..op(mnemonic, mode, cycles)
+    }
+}
+
+pub fn opcode(variant: Variant, instruction: Byte) -> &'static Opcode {
+    match variant {
+        Variant::Nmos => &NMOS_OPCODES[instruction as usize],
+        Variant::Cmos => &CMOS_OPCODES[instruction as usize],
+    }
+}
+
+// disassembles the instruction at address, returning the text and its length
+pub fn disassemble<B: Bus>(variant: Variant, memory: &B, address: Word) -> (String, Byte) {
+    let opcode = opcode(variant, memory.peek(address));
+    let byte = memory.peek(address.wrapping_add(1));
+    let word = Word::from_le_bytes([byte, memory.peek(address.wrapping_add(2))]);
+    let next = address.wrapping_add(opcode.bytes as Word);
+
+    let operand = match opcode.mode {
+        Implied => String::new(),
+        Accumulator => String::from("A"),
+        Immediate => format!("#${:02X}", byte),
+        ZeroPage => format!("${:02X}", byte),
+        ZeroPageX => format!("${:02X},X", byte),
+        ZeroPageY => format!("${:02X},Y", byte),
+        Absolute => format!("${:04X}", word),
+        AbsoluteX => format!("${:04X},X", word),
+        AbsoluteY => format!("${:04X},Y", word),
+        Indirect => format!("(${:04X})", word),
+        IndexedIndirect => format!("(${:02X},X)", byte),
+        IndirectIndexed => format!("(${:02X}),Y", byte),
+        Relative => format!("${:04X}", next.wrapping_add(byte as i8 as Word)),
+        ZeroPageIndirect => format!("(${:02X})", byte),
+        AbsoluteIndexedIndirect => format!("(${:04X},X)", word),
+        ZeroPageRelative => {
+            let offset = memory.peek(address.wrapping_add(2));
+            format!(
+                "${:02X},${:04X}",
+                byte,
+                next.wrapping_add(offset as i8 as Word)
+            )
+        }
+    };
+
+    let marker = if opcode.illegal { "*" } else { "" };
+    let text = format!("{}{} {}", marker, opcode.mnemonic, operand);
+    (text.trim_end().to_string(), opcode.bytes)
+}
+
+// the nmos 6502, undocumented opcodes are named after their usual aliases
+#[rustfmt::skip]
+pub const NMOS_OPCODES: [Opcode; 256] = [
+    op(Brk, Implied, 7),                        // 00
+    op(Ora, IndexedIndirect, 6),                // 01
+    illegal(Jam, Implied, 2),                   // 02
+    illegal(Slo, IndexedIndirect, 8),           // 03
+    illegal(Nop, ZeroPage, 3),                  // 04
+    op(Ora, ZeroPage, 3),                       // 05
+    op(Asl, ZeroPage, 5),                       // 06
+    illegal(Slo, ZeroPage, 5),                  // 07
+    op(Php, Implied, 3),                        // 08
+    op(Ora, Immediate, 2),                      // 09
+    op(Asl, Accumulator, 2),                    // 0A
+    illegal(Anc, Immediate, 2),                 // 0B
+    illegal(Nop, Absolute, 4),                  // 0C
+    op(Ora, Absolute, 4),                       // 0D
+    op(Asl, Absolute, 6),                       // 0E
+    illegal(Slo, Absolute, 6),                  // 0F
+    op(Bpl, Relative, 2),                       // 10
+    op(Ora, IndirectIndexed, 5),                // 11
+    illegal(Jam, Implied, 2),                   // 12
+    illegal(Slo, IndirectIndexed, 8),           // 13
+    illegal(Nop, ZeroPageX, 4),                 // 14
+    op(Ora, ZeroPageX, 4),                      // 15
+    op(Asl, ZeroPageX, 6),                      // 16
+    illegal(Slo, ZeroPageX, 6),                 // 17
+    op(Clc, Implied, 2),                        // 18
+    op(Ora, AbsoluteY, 4),                      // 19
+    illegal(Nop, Implied, 2),                   // 1A
+    illegal(Slo, AbsoluteY, 7),                 // 1B
+    illegal(Nop, AbsoluteX, 4),                 // 1C
+    op(Ora, AbsoluteX, 4),                      // 1D
+    op(Asl, AbsoluteX, 7),                      // 1E
+    illegal(Slo, AbsoluteX, 7),                 // 1F
+    op(Jsr, Absolute, 6),                       // 20
+    op(And, IndexedIndirect, 6),                // 21
+    illegal(Jam, Implied, 2),                   // 22
+    illegal(Rla, IndexedIndirect, 8),           // 23
+    op(Bit, ZeroPage, 3),                       // 24
+    op(And, ZeroPage, 3),                       // 25
+    op(Rol, ZeroPage, 5),                       // 26
+    illegal(Rla, ZeroPage, 5),                  // 27
+    op(Plp, Implied, 4),                        // 28
+    op(And, Immediate, 2),                      // 29
+    op(Rol, Accumulator, 2),                    // 2A
+    illegal(Anc, Immediate, 2),                 // 2B
+    op(Bit, Absolute, 4),                       // 2C
+    op(And, Absolute, 4),                       // 2D
+    op(Rol, Absolute, 6),                       // 2E
+    illegal(Rla, Absolute, 6),                  // 2F
+    op(Bmi, Relative, 2),                       // 30
+    op(And, IndirectIndexed, 5),                // 31
+    illegal(Jam, Implied, 2),                   // 32
+    illegal(Rla, IndirectIndexed, 8),           // 33
+    illegal(Nop, ZeroPageX, 4),                 // 34
+    op(And, ZeroPageX, 4),                      // 35
+    op(Rol, ZeroPageX, 6),                      // 36
+    illegal(Rla, ZeroPageX, 6),                 // 37
+    op(Sec, Implied, 2),                        // 38
+    op(And, AbsoluteY, 4),                      // 39
+    illegal(Nop, Implied, 2),                   // 3A
+    illegal(Rla, AbsoluteY, 7),                 // 3B
+    illegal(Nop, AbsoluteX, 4),                 // 3C
+    op(And, AbsoluteX, 4),                      // 3D
+    op(Rol, AbsoluteX, 7),                      // 3E
+    illegal(Rla, AbsoluteX, 7),                 // 3F
+    op(Rti, Implied, 6),                        // 40
+    op(Eor, IndexedIndirect, 6),                // 41
+    illegal(Jam, Implied, 2),                   // 42
+    illegal(Sre, IndexedIndirect, 8),           // 43
+    illegal(Nop, ZeroPage, 3),                  // 44
+    op(Eor, ZeroPage, 3),                       // 45
+    op(Lsr, ZeroPage, 5),                       // 46
+    illegal(Sre, ZeroPage, 5),                  // 47
+    op(Pha, Implied, 3),                        // 48
+    op(Eor, Immediate, 2),                      // 49
+    op(Lsr, Accumulator, 2),                    // 4A
+    illegal(Alr, Immediate, 2),                 // 4B
+    op(Jmp, Absolute, 3),                       // 4C
+    op(Eor, Absolute, 4),                       // 4D
+    op(Lsr, Absolute, 6),                       // 4E
+    illegal(Sre, Absolute, 6),                  // 4F
+    op(Bvc, Relative, 2),                       // 50
+    op(Eor, IndirectIndexed, 5),                // 51
+    illegal(Jam, Implied, 2),                   // 52
+    illegal(Sre, IndirectIndexed, 8),           // 53
+    illegal(Nop, ZeroPageX, 4),                 // 54
+    op(Eor, ZeroPageX, 4),                      // 55
+    op(Lsr, ZeroPageX, 6),                      // 56
+    illegal(Sre, ZeroPageX, 6),                 // 57
+    op(Cli, Implied, 2),                        // 58
+    op(Eor, AbsoluteY, 4),                      // 59
+    illegal(Nop, Implied, 2),                   // 5A
+    illegal(Sre, AbsoluteY, 7),                 // 5B
+    illegal(Nop, AbsoluteX, 4),                 // 5C
+    op(Eor, AbsoluteX, 4),                      // 5D
+    op(Lsr, AbsoluteX, 7),                      // 5E
+    illegal(Sre, AbsoluteX, 7),                 // 5F
+    op(Rts, Implied, 6),                        // 60
+    op(Adc, IndexedIndirect, 6),                // 61
+    illegal(Jam, Implied, 2),                   // 62
+    illegal(Rra, IndexedIndirect, 8),           // 63
+    illegal(Nop, ZeroPage, 3),                  // 64
+    op(Adc, ZeroPage, 3),                       // 65
+    op(Ror, ZeroPage, 5),                       // 66
+    illegal(Rra, ZeroPage, 5),                  // 67
+    op(Pla, Implied, 4),                        // 68
+    op(Adc, Immediate, 2),                      // 69
+    op(Ror, Accumulator, 2),                    // 6A
+    illegal(Arr, Immediate, 2),                 // 6B
+    op(Jmp, Indirect, 5),                       // 6C
+    op(Adc, Absolute, 4),                       // 6D
+    op(Ror, Absolute, 6),                       // 6E
+    illegal(Rra, Absolute, 6),                  // 6F
+    op(Bvs, Relative, 2),                       // 70
+    op(Adc, IndirectIndexed, 5),                // 71
+    illegal(Jam, Implied, 2),                   // 72
+    illegal(Rra, IndirectIndexed, 8),           // 73
+    illegal(Nop, ZeroPageX, 4),                 // 74
+    op(Adc, ZeroPageX, 4),                      // 75
+    op(Ror, ZeroPageX, 6),                      // 76
+    illegal(Rra, ZeroPageX, 6),                 // 77
+    op(Sei, Implied, 2),                        // 78
+    op(Adc, AbsoluteY, 4),                      // 79
+    illegal(Nop, Implied, 2),                   // 7A
+    illegal(Rra, AbsoluteY, 7),                 // 7B
+    illegal(Nop, AbsoluteX, 4),                 // 7C
+    op(Adc, AbsoluteX, 4),                      // 7D
+    op(Ror, AbsoluteX, 7),                      // 7E
+    illegal(Rra, AbsoluteX, 7),                 // 7F
+    illegal(Nop, Immediate, 2),                 // 80
+    op(Sta, IndexedIndirect, 6),                // 81
+    illegal(Nop, Immediate, 2),                 // 82
+    illegal(Sax, IndexedIndirect, 6),           // 83
+    op(Sty, ZeroPage, 3),                       // 84
+    op(Sta, ZeroPage, 3),                       // 85
+    op(Stx, ZeroPage, 3),                       // 86
+    illegal(Sax, ZeroPage, 3),                  // 87
+    op(Dey, Implied, 2),                        // 88
+    illegal(Nop, Immediate, 2),                 // 89
+    op(Txa, Implied, 2),                        // 8A
+    illegal(Xaa, Immediate, 2),                 // 8B
+    op(Sty, Absolute, 4),                       // 8C
+    op(Sta, Absolute, 4),                       // 8D
+    op(Stx, Absolute, 4),                       // 8E
+    illegal(Sax, Absolute, 4),                  // 8F
+    op(Bcc, Relative, 2),                       // 90
+    op(Sta, IndirectIndexed, 6),                // 91
+    illegal(Jam, Implied, 2),                   // 92
+    illegal(Sha, IndirectIndexed, 6),           // 93
+    op(Sty, ZeroPageX, 4),                      // 94
+    op(Sta, ZeroPageX, 4),                      // 95
+    op(Stx, ZeroPageY, 4),                      // 96
+    illegal(Sax, ZeroPageY, 4),                 // 97
+    op(Tya, Implied, 2),                        // 98
+    op(Sta, AbsoluteY, 5),                      // 99
+    op(Txs, Implied, 2),                        // 9A
+    illegal(Tas, AbsoluteY, 5),                 // 9B
+    illegal(Shy, AbsoluteX, 5),                 // 9C
+    op(Sta, AbsoluteX, 5),                      // 9D
+    illegal(Shx, AbsoluteY, 5),                 // 9E
+    illegal(Sha, AbsoluteY, 5),                 // 9F
+    op(Ldy, Immediate, 2),                      // A0
+    op(Lda, IndexedIndirect, 6),                // A1
+    op(Ldx, Immediate, 2),                      // A2
+    illegal(Lax, IndexedIndirect, 6),           // A3
+    op(Ldy, ZeroPage, 3),                       // A4
+    op(Lda, ZeroPage, 3),                       // A5
+    op(Ldx, ZeroPage, 3),                       // A6
+    illegal(Lax, ZeroPage, 3),                  // A7
+    op(Tay, Implied, 2),                        // A8
+    op(Lda, Immediate, 2),                      // A9
+    op(Tax, Implied, 2),                        // AA
+    illegal(Lxa, Immediate, 2),                 // AB
+    op(Ldy, Absolute, 4),                       // AC
+    op(Lda, Absolute, 4),                       // AD
+    op(Ldx, Absolute, 4),                       // AE
+    illegal(Lax, Absolute, 4),                  // AF
+    op(Bcs, Relative, 2),                       // B0
+    op(Lda, IndirectIndexed, 5),                // B1
+    illegal(Jam, Implied, 2),                   // B2
+    illegal(Lax, IndirectIndexed, 5),           // B3
+    op(Ldy, ZeroPageX, 4),                      // B4
+    op(Lda, ZeroPageX, 4),                      // B5
+    op(Ldx, ZeroPageY, 4),                      // B6
+    illegal(Lax, ZeroPageY, 4),                 // B7
+    op(Clv, Implied, 2),                        // B8
+    op(Lda, AbsoluteY, 4),                      // B9
+    op(Tsx, Implied, 2),                        // BA
+    illegal(Las, AbsoluteY, 4),                 // BB
+    op(Ldy, AbsoluteX, 4),                      // BC
+    op(Lda, AbsoluteX, 4),                      // BD
+    op(Ldx, AbsoluteY, 4),                      // BE
+    illegal(Lax, AbsoluteY, 4),                 // BF
+    op(Cpy, Immediate, 2),                      // C0
+    op(Cmp, IndexedIndirect, 6),                // C1
+    illegal(Nop, Immediate, 2),                 // C2
+    illegal(Dcp, IndexedIndirect, 8),           // C3
+    op(Cpy, ZeroPage, 3),                       // C4
+    op(Cmp, ZeroPage, 3),                       // C5
+    op(Dec, ZeroPage, 5),                       // C6
+    illegal(Dcp, ZeroPage, 5),                  // C7
+    op(Iny, Implied, 2),                        // C8
+    op(Cmp, Immediate, 2),                      // C9
+    op(Dex, Implied, 2),                        // CA
+    illegal(Sbx, Immediate, 2),                 // CB
+    op(Cpy, Absolute, 4),                       // CC
+    op(Cmp, Absolute, 4),                       // CD
+    op(Dec, Absolute, 6),                       // CE
+    illegal(Dcp, Absolute, 6),                  // CF
+    op(Bne, Relative, 2),                       // D0
+    op(Cmp, IndirectIndexed, 5),                // D1
+    illegal(Jam, Implied, 2),                   // D2
+    illegal(Dcp, IndirectIndexed, 8),           // D3
+    illegal(Nop, ZeroPageX, 4),                 // D4
+    op(Cmp, ZeroPageX, 4),                      // D5
+    op(Dec, ZeroPageX, 6),                      // D6
+    illegal(Dcp, ZeroPageX, 6),                 // D7
+    op(Cld, Implied, 2),                        // D8
+    op(Cmp, AbsoluteY, 4),                      // D9
+    illegal(Nop, Implied, 2),                   // DA
+    illegal(Dcp, AbsoluteY, 7),                 // DB
+    illegal(Nop, AbsoluteX, 4),                 // DC
+    op(Cmp, AbsoluteX, 4),                      // DD
+    op(Dec, AbsoluteX, 7),                      // DE
+    illegal(Dcp, AbsoluteX, 7),                 // DF
+    op(Cpx, Immediate, 2),                      // E0
+    op(Sbc, IndexedIndirect, 6),                // E1
+    illegal(Nop, Immediate, 2),                 // E2
+    illegal(Isc, IndexedIndirect, 8),           // E3
+    op(Cpx, ZeroPage, 3),                       // E4
+    op(Sbc, ZeroPage, 3),                       // E5
+    op(Inc, ZeroPage, 5),                       // E6
+    illegal(Isc, ZeroPage, 5),                  // E7
+    op(Inx, Implied, 2),                        // E8
+    op(Sbc, Immediate, 2),                      // E9
+    op(Nop, Implied, 2),                        // EA
+    illegal(Sbc, Immediate, 2),                 // EB
+    op(Cpx, Absolute, 4),                       // EC
+    op(Sbc, Absolute, 4),                       // ED
+    op(Inc, Absolute, 6),                       // EE
+    illegal(Isc, Absolute, 6),                  // EF
+    op(Beq, Relative, 2),                       // F0
+    op(Sbc, IndirectIndexed, 5),                // F1
+    illegal(Jam, Implied, 2),                   // F2
+    illegal(Isc, IndirectIndexed, 8),           // F3
+    illegal(Nop, ZeroPageX, 4),                 // F4
+    op(Sbc, ZeroPageX, 4),                      // F5
+    op(Inc, ZeroPageX, 6),                      // F6
+    illegal(Isc, ZeroPageX, 6),                 // F7
+    op(Sed, Implied, 2),                        // F8
+    op(Sbc, AbsoluteY, 4),                      // F9
+    illegal(Nop, Implied, 2),                   // FA
+    illegal(Isc, AbsoluteY, 7),                 // FB
+    illegal(Nop, AbsoluteX, 4),                 // FC
+    op(Sbc, AbsoluteX, 4),                      // FD
+    op(Inc, AbsoluteX, 7),                      // FE
+    illegal(Isc, AbsoluteX, 7),                 // FF
+];
+
+// the 65c02, where every undefined opcode is a nop of some length
+#[rustfmt::skip]
+pub const CMOS_OPCODES: [Opcode; 256] = [
+    op(Brk, Implied, 7),                        // 00
+    op(Ora, IndexedIndirect, 6),                // 01
+    illegal(Nop, Immediate, 2),                 // 02
+    illegal(Nop, Implied, 1),                   // 03
+    op(Tsb, ZeroPage, 5),                       // 04
+    op(Ora, ZeroPage, 3),                       // 05
+    op(Asl, ZeroPage, 5),                       // 06
+    op(Rmb(0), ZeroPage, 5),                    // 07
+    op(Php, Implied, 3),                        // 08
+    op(Ora, Immediate, 2),                      // 09
+    op(Asl, Accumulator, 2),                    // 0A
+    illegal(Nop, Implied, 1),                   // 0B
+    op(Tsb, Absolute, 6),                       // 0C
+    op(Ora, Absolute, 4),                       // 0D
+    op(Asl, Absolute, 6),                       // 0E
+    op(Bbr(0), ZeroPageRelative, 5),            // 0F
+    op(Bpl, Relative, 2),                       // 10
+    op(Ora, IndirectIndexed, 5),                // 11
+    op(Ora, ZeroPageIndirect, 5),               // 12
+    illegal(Nop, Implied, 1),                   // 13
+    op(Trb, ZeroPage, 5),                       // 14
+    op(Ora, ZeroPageX, 4),                      // 15
+    op(Asl, ZeroPageX, 6),                      // 16
+    op(Rmb(1), ZeroPage, 5),                    // 17
+    op(Clc, Implied, 2),                        // 18
+    op(Ora, AbsoluteY, 4),                      // 19
+    op(Inc, Accumulator, 2),                    // 1A
+    illegal(Nop, Implied, 1),                   // 1B
+    op(Trb, Absolute, 6),                       // 1C
+    op(Ora, AbsoluteX, 4),                      // 1D
+    op(Asl, AbsoluteX, 6),                      // 1E
+    op(Bbr(1), ZeroPageRelative, 5),            // 1F
+    op(Jsr, Absolute, 6),                       // 20
+    op(And, IndexedIndirect, 6),                // 21
+    illegal(Nop, Immediate, 2),                 // 22
+    illegal(Nop, Implied, 1),                   // 23
+    op(Bit, ZeroPage, 3),                       // 24
+    op(And, ZeroPage, 3),                       // 25
+    op(Rol, ZeroPage, 5),                       // 26
+    op(Rmb(2), ZeroPage, 5),                    // 27
+    op(Plp, Implied, 4),                        // 28
+    op(And, Immediate, 2),                      // 29
+    op(Rol, Accumulator, 2),                    // 2A
+    illegal(Nop, Implied, 1),                   // 2B
+    op(Bit, Absolute, 4),                       // 2C
+    op(And, Absolute, 4),                       // 2D
+    op(Rol, Absolute, 6),                       // 2E
+    op(Bbr(2), ZeroPageRelative, 5),            // 2F
+    op(Bmi, Relative, 2),                       // 30
+    op(And, IndirectIndexed, 5),                // 31
+    op(And, ZeroPageIndirect, 5),               // 32
+    illegal(Nop, Implied, 1),                   // 33
+    op(Bit, ZeroPageX, 4),                      // 34
+    op(And, ZeroPageX, 4),                      // 35
+    op(Rol, ZeroPageX, 6),                      // 36
+    op(Rmb(3), ZeroPage, 5),                    // 37
+    op(Sec, Implied, 2),                        // 38
+    op(And, AbsoluteY, 4),                      // 39
+    op(Dec, Accumulator, 2),                    // 3A
+    illegal(Nop, Implied, 1),                   // 3B
+    op(Bit, AbsoluteX, 4),                      // 3C
+    op(And, AbsoluteX, 4),                      // 3D
+    op(Rol, AbsoluteX, 6),                      // 3E
+    op(Bbr(3), ZeroPageRelative, 5),            // 3F
+    op(Rti, Implied, 6),                        // 40
+    op(Eor, IndexedIndirect, 6),                // 41
+    illegal(Nop, Immediate, 2),                 // 42
+    illegal(Nop, Implied, 1),                   // 43
+    illegal(Nop, ZeroPage, 3),                  // 44
+    op(Eor, ZeroPage, 3),                       // 45
+    op(Lsr, ZeroPage, 5),                       // 46
+    op(Rmb(4), ZeroPage, 5),                    // 47
+    op(Pha, Implied, 3),                        // 48
+    op(Eor, Immediate, 2),                      // 49
+    op(Lsr, Accumulator, 2),                    // 4A
+    illegal(Nop, Implied, 1),                   // 4B
+    op(Jmp, Absolute, 3),                       // 4C
+    op(Eor, Absolute, 4),                       // 4D
+    op(Lsr, Absolute, 6),                       // 4E
+    op(Bbr(4), ZeroPageRelative, 5),            // 4F
+    op(Bvc, Relative, 2),                       // 50
+    op(Eor, IndirectIndexed, 5),                // 51
+    op(Eor, ZeroPageIndirect, 5),               // 52
+    illegal(Nop, Implied, 1),                   // 53
+    illegal(Nop, ZeroPageX, 4),                 // 54
+    op(Eor, ZeroPageX, 4),                      // 55
+    op(Lsr, ZeroPageX, 6),                      // 56
+    op(Rmb(5), ZeroPage, 5),                    // 57
+    op(Cli, Implied, 2),                        // 58
+    op(Eor, AbsoluteY, 4),                      // 59
+    op(Phy, Implied, 3),                        // 5A
+    illegal(Nop, Implied, 1),                   // 5B
+    illegal(Nop, Absolute, 8),                  // 5C
+    op(Eor, AbsoluteX, 4),                      // 5D
+    op(Lsr, AbsoluteX, 6),                      // 5E
+    op(Bbr(5), ZeroPageRelative, 5),            // 5F
+    op(Rts, Implied, 6),                        // 60
+    op(Adc, IndexedIndirect, 6),                // 61
+    illegal(Nop, Immediate, 2),                 // 62
+    illegal(Nop, Implied, 1),                   // 63
+    op(Stz, ZeroPage, 3),                       // 64
+    op(Adc, ZeroPage, 3),                       // 65
+    op(Ror, ZeroPage, 5),                       // 66
+    op(Rmb(6), ZeroPage, 5),                    // 67
+    op(Pla, Implied, 4),                        // 68
+    op(Adc, Immediate, 2),                      // 69
+    op(Ror, Accumulator, 2),                    // 6A
+    illegal(Nop, Implied, 1),                   // 6B
+    op(Jmp, Indirect, 6),                       // 6C
+    op(Adc, Absolute, 4),                       // 6D
+    op(Ror, Absolute, 6),                       // 6E
+    op(Bbr(6), ZeroPageRelative, 5),            // 6F
+    op(Bvs, Relative, 2),                       // 70
+    op(Adc, IndirectIndexed, 5),                // 71
+    op(Adc, ZeroPageIndirect, 5),               // 72
+    illegal(Nop, Implied, 1),                   // 73
+    op(Stz, ZeroPageX, 4),                      // 74
+    op(Adc, ZeroPageX, 4),                      // 75
+    op(Ror, ZeroPageX, 6),                      // 76
+    op(Rmb(7), ZeroPage, 5),                    // 77
+    op(Sei, Implied, 2),                        // 78
+    op(Adc, AbsoluteY, 4),                      // 79
+    op(Ply, Implied, 4),                        // 7A
+    illegal(Nop, Implied, 1),                   // 7B
+    op(Jmp, AbsoluteIndexedIndirect, 6),        // 7C
+    op(Adc, AbsoluteX, 4),                      // 7D
+    op(Ror, AbsoluteX, 6),                      // 7E
+    op(Bbr(7), ZeroPageRelative, 5),            // 7F
+    op(Bra, Relative, 2),                       // 80
+    op(Sta, IndexedIndirect, 6),                // 81
+    illegal(Nop, Immediate, 2),                 // 82
+    illegal(Nop, Implied, 1),                   // 83
+    op(Sty, ZeroPage, 3),                       // 84
+    op(Sta, ZeroPage, 3),                       // 85
+    op(Stx, ZeroPage, 3),                       // 86
+    op(Smb(0), ZeroPage, 5),                    // 87
+    op(Dey, Implied, 2),                        // 88
+    op(Bit, Immediate, 2),                      // 89
+    op(Txa, Implied, 2),                        // 8A
+    illegal(Nop, Implied, 1),                   // 8B
+    op(Sty, Absolute, 4),                       // 8C
+    op(Sta, Absolute, 4),                       // 8D
+    op(Stx, Absolute, 4),                       // 8E
+    op(Bbs(0), ZeroPageRelative, 5),            // 8F
+    op(Bcc, Relative, 2),                       // 90
+    op(Sta, IndirectIndexed, 6),                // 91
+    op(Sta, ZeroPageIndirect, 5),               // 92
+    illegal(Nop, Implied, 1),                   // 93
+    op(Sty, ZeroPageX, 4),                      // 94
+    op(Sta, ZeroPageX, 4),                      // 95
+    op(Stx, ZeroPageY, 4),                      // 96
+    op(Smb(1), ZeroPage, 5),                    // 97
+    op(Tya, Implied, 2),                        // 98
+    op(Sta, AbsoluteY, 5),                      // 99
+    op(Txs, Implied, 2),                        // 9A
+    illegal(Nop, Implied, 1),                   // 9B
+    op(Stz, Absolute, 4),                       // 9C
+    op(Sta, AbsoluteX, 5),                      // 9D
+    op(Stz, AbsoluteX, 5),                      // 9E
+    op(Bbs(1), ZeroPageRelative, 5),            // 9F
+    op(Ldy, Immediate, 2),                      // A0
+    op(Lda, IndexedIndirect, 6),                // A1
+    op(Ldx, Immediate, 2),                      // A2
+    illegal(Nop, Implied, 1),                   // A3
+    op(Ldy, ZeroPage, 3),                       // A4
+    op(Lda, ZeroPage, 3),                       // A5
+    op(Ldx, ZeroPage, 3),                       // A6
+    op(Smb(2), ZeroPage, 5),                    // A7
+    op(Tay, Implied, 2),                        // A8
+    op(Lda, Immediate, 2),                      // A9
+    op(Tax, Implied, 2),                        // AA
+    illegal(Nop, Implied, 1),                   // AB
+    op(Ldy, Absolute, 4),                       // AC
+    op(Lda, Absolute, 4),                       // AD
+    op(Ldx, Absolute, 4),                       // AE
+    op(Bbs(2), ZeroPageRelative, 5),            // AF
+    op(Bcs, Relative, 2),                       // B0
+    op(Lda, IndirectIndexed, 5),                // B1
+    op(Lda, ZeroPageIndirect, 5),               // B2
+    illegal(Nop, Implied, 1),                   // B3
+    op(Ldy, ZeroPageX, 4),                      // B4
+    op(Lda, ZeroPageX, 4),                      // B5
+    op(Ldx, ZeroPageY, 4),                      // B6
+    op(Smb(3), ZeroPage, 5),                    // B7
+    op(Clv, Implied, 2),                        // B8
+    op(Lda, AbsoluteY, 4),                      // B9
+    op(Tsx, Implied, 2),                        // BA
+    illegal(Nop, Implied, 1),                   // BB
+    op(Ldy, AbsoluteX, 4),                      // BC
+    op(Lda, AbsoluteX, 4),                      // BD
+    op(Ldx, AbsoluteY, 4),                      // BE
+    op(Bbs(3), ZeroPageRelative, 5),            // BF
+    op(Cpy, Immediate, 2),                      // C0
+    op(Cmp, IndexedIndirect, 6),                // C1
+    illegal(Nop, Immediate, 2),                 // C2
+    illegal(Nop, Implied, 1),                   // C3
+    op(Cpy, ZeroPage, 3),                       // C4
+    op(Cmp, ZeroPage, 3),                       // C5
+    op(Dec, ZeroPage, 5),                       // C6
+    op(Smb(4), ZeroPage, 5),                    // C7
+    op(Iny, Implied, 2),                        // C8
+    op(Cmp, Immediate, 2),                      // C9
+    op(Dex, Implied, 2),                        // CA
+    op(Wai, Implied, 3),                        // CB
+    op(Cpy, Absolute, 4),                       // CC
+    op(Cmp, Absolute, 4),                       // CD
+    op(Dec, Absolute, 6),                       // CE
+    op(Bbs(4), ZeroPageRelative, 5),            // CF
+    op(Bne, Relative, 2),                       // D0
+    op(Cmp, IndirectIndexed, 5),                // D1
+    op(Cmp, ZeroPageIndirect, 5),               // D2
+    illegal(Nop, Implied, 1),                   // D3
+    illegal(Nop, ZeroPageX, 4),                 // D4
+    op(Cmp, ZeroPageX, 4),                      // D5
+    op(Dec, ZeroPageX, 6),                      // D6
+    op(Smb(5), ZeroPage, 5),                    // D7
+    op(Cld, Implied, 2),                        // D8
+    op(Cmp, AbsoluteY, 4),                      // D9
+    op(Phx, Implied, 3),                        // DA
+    op(Stp, Implied, 3),                        // DB
+    illegal(Nop, Absolute, 4),                  // DC
+    op(Cmp, AbsoluteX, 4),                      // DD
+    op(Dec, AbsoluteX, 7),                      // DE
+    op(Bbs(5), ZeroPageRelative, 5),            // DF
+    op(Cpx, Immediate, 2),                      // E0
+    op(Sbc, IndexedIndirect, 6),                // E1
+    illegal(Nop, Immediate, 2),                 // E2
+    illegal(Nop, Implied, 1),                   // E3
+    op(Cpx, ZeroPage, 3),                       // E4
+    op(Sbc, ZeroPage, 3),                       // E5
+    op(Inc, ZeroPage, 5),                       // E6
+    op(Smb(6), ZeroPage, 5),                    // E7
+    op(Inx, Implied, 2),                        // E8
+    op(Sbc, Immediate, 2),                      // E9
+    op(Nop, Implied, 2),                        // EA
+    illegal(Nop, Implied, 1),                   // EB
+    op(Cpx, Absolute, 4),                       // EC
+    op(Sbc, Absolute, 4),                       // ED
+    op(Inc, Absolute, 6),                       // EE
+    op(Bbs(6), ZeroPageRelative, 5),            // EF
+    op(Beq, Relative, 2),                       // F0
+    op(Sbc, IndirectIndexed, 5),                // F1
+    op(Sbc, ZeroPageIndirect, 5),               // F2
+    illegal(Nop, Implied, 1),                   // F3
+    illegal(Nop, ZeroPageX, 4),                 // F4
+    op(Sbc, ZeroPageX, 4),                      // F5
+    op(Inc, ZeroPageX, 6),                      // F6
+    op(Smb(7), ZeroPage, 5),                    // F7
+    op(Sed, Implied, 2),                        // F8
+    op(Sbc, AbsoluteY, 4),                      // F9
+    op(Plx, Implied, 4),                        // FA
+    illegal(Nop, Implied, 1),                   // FB
+    illegal(Nop, Absolute, 4),                  // FC
+    op(Sbc, AbsoluteX, 4),                      // FD
+    op(Inc, AbsoluteX, 7),                      // FE
+    op(Bbs(7), ZeroPageRelative, 5),            // FF
+];
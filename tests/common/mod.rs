@@ -0,0 +1,48 @@
+// each test binary only uses some of these helpers
+#![allow(dead_code)]
+
+use std::fs;
+
+use emu6502::cpu::{load_memory, CPU, MEMORY};
+use emu6502::Word;
+
+// the suites finish well within this many instructions, anything more is a runaway
+const INSTRUCTION_LIMIT: u64 = 200_000_000;
+
+// loads a 64k test image and points the cpu at its entry point
+pub fn load_image(cpu: &mut CPU, memory: &mut MEMORY, image: &str, entry: Word) {
+    load_memory(memory, image);
+    cpu.prgmctr = entry;
+}
+
+// copies a raw program image into memory starting at address
+pub fn load_program(memory: &mut MEMORY, program: &str, address: Word) {
+    let bytes = fs::read(program).unwrap();
+    for (offset, byte) in bytes.iter().enumerate() {
+        memory.set_byte(address + offset as Word, *byte);
+    }
+}
+
+// runs until an instruction jumps or branches to itself and returns where it stopped
+pub fn run_until_trap(cpu: &mut CPU, memory: &mut MEMORY) -> Word {
+    for _ in 0..INSTRUCTION_LIMIT {
+        let address = cpu.prgmctr;
+        cpu.execute(memory);
+        if cpu.prgmctr == address {
+            return address;
+        }
+    }
+    panic!("no trap after {} instructions", INSTRUCTION_LIMIT);
+}
+
+// finds the listing line assembled at address along with the source leading up to it
+pub fn describe_trap(listing: &str, address: Word) -> String {
+    let listing = fs::read_to_string(listing).unwrap();
+    let lines: Vec<&str> = listing.lines().collect();
+    let prefix = format!("{:04x} :", address);
+
+    match lines.iter().position(|line| line.starts_with(&prefix)) {
+        Some(index) => lines[index.saturating_sub(12)..=index].join("\n"),
+        None => format!("{:04x} not found in listing", address),
+    }
+}
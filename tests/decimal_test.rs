@@ -0,0 +1,47 @@
+mod common;
+
+use emu6502::cpu::{Variant, CPU, MEMORY};
+use emu6502::Word;
+
+const START: Word = 0x0200;
+const TRAP: Word = 0x0203;
+
+// zero page variables of the test program
+const ERROR: Word = 0x04;
+const N1: Word = 0x07;
+const N2: Word = 0x0A;
+const DA: Word = 0x02;
+const AR: Word = 0x00;
+const DNVZC: Word = 0x03;
+
+fn run_decimal_test(variant: Variant, program: &str) {
+    let mut cpu = CPU::with_variant(variant);
+    let mut memory = MEMORY::new();
+    common::load_program(&mut memory, program, START);
+    cpu.prgmctr = START;
+
+    let trap = common::run_until_trap(&mut cpu, &mut memory);
+
+    assert_eq!(trap, TRAP);
+    assert_eq!(
+        memory.get_byte(ERROR),
+        0,
+        "{:02x} and {:02x} with carry {} gave {:02x} flags {:02x}, expected {:02x}",
+        memory.get_byte(N1),
+        memory.get_byte(N2),
+        cpu.y,
+        memory.get_byte(DA),
+        memory.get_byte(DNVZC),
+        memory.get_byte(AR)
+    );
+}
+
+#[test]
+fn test_6502_decimal_mode() {
+    run_decimal_test(Variant::Nmos, "tests/6502_decimal_test.bin");
+}
+
+#[test]
+fn test_65c02_decimal_mode() {
+    run_decimal_test(Variant::Cmos, "tests/65C02_decimal_test.bin");
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use emu6502::cpu::{Variant, CPU, MEMORY};
+use emu6502::Word;
+
+const IMAGE: &str = "tests/65C02_extended_opcodes_test.bin";
+const LISTING: &str = "tests/65C02_extended_opcodes_test.lst";
+
+const CODE_SEGMENT: Word = 0x0400;
+const TEST_CASE: Word = 0x0202;
+const SUCCESS: Word = 0x24F1;
+
+#[test]
+fn test_65c02_extended_opcodes() {
+    let mut cpu = CPU::with_variant(Variant::Cmos);
+    let mut memory = MEMORY::new();
+    common::load_image(&mut cpu, &mut memory, IMAGE, CODE_SEGMENT);
+
+    let trap = common::run_until_trap(&mut cpu, &mut memory);
+
+    assert_eq!(
+        trap,
+        SUCCESS,
+        "trapped at {:04x} in test case {:02x}\n{}",
+        trap,
+        memory.get_byte(TEST_CASE),
+        common::describe_trap(LISTING, trap)
+    );
+}
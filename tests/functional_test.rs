@@ -0,0 +1,29 @@
+mod common;
+
+use emu6502::cpu::{CPU, MEMORY};
+use emu6502::Word;
+
+const IMAGE: &str = "tests/6502_functional_test.bin";
+const LISTING: &str = "tests/6502_functional_test.lst";
+
+const CODE_SEGMENT: Word = 0x0400;
+const TEST_CASE: Word = 0x0200;
+const SUCCESS: Word = 0x3469;
+
+#[test]
+fn test_6502_functional() {
+    let mut cpu = CPU::new();
+    let mut memory = MEMORY::new();
+    common::load_image(&mut cpu, &mut memory, IMAGE, CODE_SEGMENT);
+
+    let trap = common::run_until_trap(&mut cpu, &mut memory);
+
+    assert_eq!(
+        trap,
+        SUCCESS,
+        "trapped at {:04x} in test case {:02x}\n{}",
+        trap,
+        memory.get_byte(TEST_CASE),
+        common::describe_trap(LISTING, trap)
+    );
+}